@@ -0,0 +1,177 @@
+//! Filter audit: a rate-limited sample of dropped entries.
+//!
+//! Every filter drop site reports to [`FilterAudit::sample`] with a reason.
+//! Up to `samples` entries per source and reason are kept per `interval`
+//! and logged, or appended as JSON Lines to a dedicated file, so filter
+//! settings can be checked against real traffic.
+
+use crate::config::FilterAuditConfig;
+use crate::journal::JournalEntry;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+/// Characters of `MESSAGE` kept in an audit record.
+const MAX_AUDIT_MESSAGE_CHARS: usize = 512;
+
+/// Start and count of the current sample window per (source, reason).
+type SampleWindows = HashMap<(String, &'static str), (Instant, usize)>;
+
+/// Shared sampler and sink for dropped entries.
+pub struct FilterAudit {
+    samples: usize,
+    interval: Duration,
+    file: Option<Mutex<File>>,
+    windows: Mutex<SampleWindows>,
+}
+
+impl FilterAudit {
+    /// Build the audit, opening `config.path` for appending if set.
+    pub fn new(config: &FilterAuditConfig) -> std::io::Result<Self> {
+        let file = match &config.path {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+        Ok(Self {
+            samples: config.samples,
+            interval: config.interval,
+            file,
+            windows: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Record a sample of `dropped` for `source` under `reason`, within the
+    /// per-interval budget.
+    pub fn sample(&self, source: &str, reason: &'static str, dropped: &[JournalEntry]) {
+        let take = self.take_budget(source, reason, dropped.len(), Instant::now());
+        for entry in &dropped[..take] {
+            self.write(source, reason, entry);
+        }
+    }
+
+    /// How many of `wanted` samples fit in the current window.
+    fn take_budget(
+        &self,
+        source: &str,
+        reason: &'static str,
+        wanted: usize,
+        now: Instant,
+    ) -> usize {
+        let mut windows = self.windows.lock();
+        let (start, taken) = windows
+            .entry((source.to_string(), reason))
+            .or_insert((now, 0));
+        if now.duration_since(*start) >= self.interval {
+            *start = now;
+            *taken = 0;
+        }
+        let take = wanted.min(self.samples.saturating_sub(*taken));
+        *taken += take;
+        take
+    }
+
+    fn write(&self, source: &str, reason: &str, entry: &JournalEntry) {
+        let message: String = entry
+            .message
+            .chars()
+            .take(MAX_AUDIT_MESSAGE_CHARS)
+            .collect();
+        match &self.file {
+            Some(file) => {
+                let record = serde_json::json!({
+                    "time": humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+                    "source": source,
+                    "reason": reason,
+                    "cursor": entry.cursor,
+                    "realtime_timestamp": entry.realtime_timestamp,
+                    "priority": entry.priority,
+                    "unit": entry.systemd_unit,
+                    "message": message,
+                });
+                if let Err(e) = writeln!(file.lock(), "{}", record) {
+                    warn!(error = %e, "Failed to write filter audit record");
+                }
+            }
+            None => info!(
+                source = source,
+                reason = reason,
+                cursor = %entry.cursor,
+                priority = ?entry.priority,
+                unit = ?entry.systemd_unit,
+                message = %message,
+                "Filter audit: dropped entry"
+            ),
+        }
+    }
+}
+
+impl std::fmt::Debug for FilterAudit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FilterAudit")
+            .field("samples", &self.samples)
+            .field("interval", &self.interval)
+            .field("file", &self.file.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audit(samples: usize, path: Option<std::path::PathBuf>) -> FilterAudit {
+        FilterAudit::new(&FilterAuditConfig {
+            samples,
+            interval: Duration::from_secs(60),
+            path,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_budget_per_source_and_reason() {
+        let audit = audit(3, None);
+        let now = Instant::now();
+        assert_eq!(audit.take_budget("a", "stale", 2, now), 2);
+        assert_eq!(audit.take_budget("a", "stale", 5, now), 1);
+        assert_eq!(audit.take_budget("a", "stale", 5, now), 0);
+        // Separate budgets per reason and per source
+        assert_eq!(audit.take_budget("a", "uid_filter", 5, now), 3);
+        assert_eq!(audit.take_budget("b", "stale", 1, now), 1);
+        // The window resets after the interval
+        let later = now + Duration::from_secs(61);
+        assert_eq!(audit.take_budget("a", "stale", 5, later), 3);
+    }
+
+    #[test]
+    fn test_writes_json_lines() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let audit = audit(1, Some(path.clone()));
+        let entries: Vec<JournalEntry> = (1..=2)
+            .map(|i| JournalEntry {
+                cursor: format!("s=abc;i={}", i),
+                message: "x".repeat(1000),
+                ..Default::default()
+            })
+            .collect();
+        audit.sample("host-01", "stale", &entries);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(record["source"], "host-01");
+        assert_eq!(record["reason"], "stale");
+        assert_eq!(record["cursor"], "s=abc;i=1");
+        assert_eq!(
+            record["message"].as_str().unwrap().len(),
+            MAX_AUDIT_MESSAGE_CHARS
+        );
+    }
+}
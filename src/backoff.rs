@@ -0,0 +1,167 @@
+//! Exponential backoff with configurable jitter.
+//!
+//! Shared by every retry path so the delay math and jitter behavior live in
+//! one place. Jitter strategies follow the AWS Architecture Blog post
+//! "Exponential Backoff And Jitter".
+
+use crate::config::RetryJitter;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Largest exponent applied to the base delay before capping.
+const MAX_EXPONENT: u32 = 16;
+
+/// Exponential backoff state for one retry sequence.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    jitter: RetryJitter,
+    /// Previous delay, used by decorrelated jitter.
+    prev: Duration,
+    rng: u64,
+}
+
+impl Backoff {
+    /// Create a backoff starting at `base` and never exceeding `max`.
+    pub fn new(base: Duration, max: Duration, jitter: RetryJitter) -> Self {
+        Self {
+            base,
+            max,
+            jitter,
+            prev: base,
+            rng: seed(),
+        }
+    }
+
+    /// Delay before retry number `attempt` (1-based).
+    ///
+    /// The un-jittered delay is `base * 2^(attempt - 1)`, capped at `max`.
+    /// Decorrelated jitter ignores `attempt` and grows from the previous delay.
+    pub fn delay(&mut self, attempt: u32) -> Duration {
+        let exp = self
+            .base
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1).min(MAX_EXPONENT)))
+            .min(self.max);
+
+        match self.jitter {
+            RetryJitter::None => exp,
+            RetryJitter::Full => self.between(Duration::ZERO, exp),
+            RetryJitter::Equal => {
+                let half = exp / 2;
+                half + self.between(Duration::ZERO, exp - half)
+            }
+            RetryJitter::Decorrelated => {
+                let upper = self.prev.saturating_mul(3).max(self.base);
+                let delay = self.between(self.base, upper).min(self.max);
+                self.prev = delay;
+                delay
+            }
+        }
+    }
+
+    /// Start a new retry sequence (after a success).
+    pub fn reset(&mut self) {
+        self.prev = self.base;
+    }
+
+    /// Uniform duration in `[low, high]`.
+    fn between(&mut self, low: Duration, high: Duration) -> Duration {
+        if high <= low {
+            return low;
+        }
+        let span = (high - low).as_nanos() as f64;
+        low + Duration::from_nanos((self.next_unit() * span) as u64)
+    }
+
+    /// Uniform float in `[0, 1]` from a xorshift64* generator.
+    fn next_unit(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let value = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Per-instance random seed; std's `RandomState` keys are randomized per
+/// process and per instance, so sources don't retry in lockstep.
+fn seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    hasher.finish() | 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: Duration = Duration::from_millis(100);
+    const MAX: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn test_no_jitter_is_exponential() {
+        let mut backoff = Backoff::new(BASE, MAX, RetryJitter::None);
+        assert_eq!(backoff.delay(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay(2), Duration::from_millis(200));
+        assert_eq!(backoff.delay(3), Duration::from_millis(400));
+        assert_eq!(backoff.delay(40), MAX);
+    }
+
+    #[test]
+    fn test_full_jitter_bounds() {
+        let mut backoff = Backoff::new(BASE, MAX, RetryJitter::Full);
+        for attempt in 1..=10 {
+            let cap = BASE.saturating_mul(1 << (attempt - 1)).min(MAX);
+            for _ in 0..100 {
+                assert!(backoff.delay(attempt) <= cap);
+            }
+        }
+        // Spread across the whole range rather than sticking to one value
+        let delays: Vec<Duration> = (0..100).map(|_| backoff.delay(8)).collect();
+        assert!(delays.iter().any(|d| *d < Duration::from_secs(3)));
+        assert!(delays.iter().any(|d| *d > Duration::from_secs(7)));
+    }
+
+    #[test]
+    fn test_equal_jitter_bounds() {
+        let mut backoff = Backoff::new(BASE, MAX, RetryJitter::Equal);
+        for attempt in 1..=10 {
+            let cap = BASE.saturating_mul(1 << (attempt - 1)).min(MAX);
+            for _ in 0..100 {
+                let delay = backoff.delay(attempt);
+                assert!(delay >= cap / 2 && delay <= cap, "{:?} vs {:?}", delay, cap);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_bounds() {
+        let mut backoff = Backoff::new(BASE, MAX, RetryJitter::Decorrelated);
+        let mut prev = BASE;
+        for _ in 0..1000 {
+            let delay = backoff.delay(1);
+            assert!(delay >= BASE, "{:?} below base", delay);
+            assert!(
+                delay <= (prev * 3).min(MAX),
+                "{:?} above 3x {:?}",
+                delay,
+                prev
+            );
+            prev = delay;
+        }
+
+        backoff.reset();
+        assert!(backoff.delay(1) <= BASE * 3);
+    }
+
+    #[test]
+    fn test_instances_are_independently_seeded() {
+        let mut a = Backoff::new(BASE, MAX, RetryJitter::Full);
+        let mut b = Backoff::new(BASE, MAX, RetryJitter::Full);
+        let a: Vec<Duration> = (0..8).map(|_| a.delay(10)).collect();
+        let b: Vec<Duration> = (0..8).map(|_| b.delay(10)).collect();
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,275 @@
+//! Synthetic throughput benchmark for the OTLP send path.
+//!
+//! Generates journal entries in memory and pushes them through the real
+//! [`OtlpClient`] (mapping, serialization, compression, HTTP), so an OTLP
+//! endpoint can be sized without a gatewayd in the loop.
+
+use crate::config::Config;
+use crate::journal::JournalEntry;
+use crate::otlp::{Batch, OtlpClient, OtlpError, SourcePayloadOptions};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+/// Source name used for the synthetic batches.
+const BENCHMARK_SOURCE: &str = "benchmark";
+/// Distinct `_SYSTEMD_UNIT` values, so batches group like real traffic.
+const SYNTHETIC_UNITS: usize = 4;
+
+/// How long and how hard to push.
+#[derive(Debug, Clone)]
+pub struct BenchmarkOptions {
+    /// Stop sending after this long.
+    pub duration: Duration,
+    /// Target records per second; `None` sends as fast as possible.
+    pub rate: Option<u64>,
+    /// Records per request.
+    pub batch_size: usize,
+}
+
+/// Outcome of a benchmark run.
+#[derive(Debug, Default)]
+pub struct BenchmarkReport {
+    pub batches: u64,
+    pub records: u64,
+    pub failed_batches: u64,
+    pub elapsed: Duration,
+    /// Per-request latencies, sorted ascending.
+    pub latencies: Vec<Duration>,
+}
+
+impl BenchmarkReport {
+    /// Records successfully sent per second.
+    pub fn records_per_sec(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.records as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Fraction of requests that failed.
+    pub fn error_rate(&self) -> f64 {
+        if self.batches == 0 {
+            return 0.0;
+        }
+        self.failed_batches as f64 / self.batches as f64
+    }
+
+    /// Latency at percentile `p` (0-100), nearest-rank.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = ((p / 100.0) * self.latencies.len() as f64).ceil() as usize;
+        Some(self.latencies[rank.clamp(1, self.latencies.len()) - 1])
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |p: f64| {
+            self.percentile(p)
+                .map(|d| format!("{:.1}ms", d.as_secs_f64() * 1000.0))
+                .unwrap_or_else(|| "-".to_string())
+        };
+        writeln!(f, "Benchmark results:")?;
+        writeln!(f, "  Duration: {:.1}s", self.elapsed.as_secs_f64())?;
+        writeln!(
+            f,
+            "  Requests: {} ({} failed, {:.2}% error rate)",
+            self.batches,
+            self.failed_batches,
+            self.error_rate() * 100.0
+        )?;
+        writeln!(f, "  Records sent: {}", self.records)?;
+        writeln!(f, "  Throughput: {:.0} records/s", self.records_per_sec())?;
+        write!(
+            f,
+            "  Latency: p50 {}, p90 {}, p99 {}, max {}",
+            ms(50.0),
+            ms(90.0),
+            ms(99.0),
+            ms(100.0)
+        )
+    }
+}
+
+/// Benchmark the OTLP endpoint described by `config` until `options.duration`
+/// elapses or `shutdown` is raised.
+pub fn run(
+    config: &Config,
+    options: &BenchmarkOptions,
+    shutdown: &AtomicBool,
+) -> Result<BenchmarkReport, OtlpError> {
+    let client = crate::build_otlp_client(config, None)?;
+    info!(
+        otlp_endpoint = %config.otlp_endpoint,
+        duration = %humantime::format_duration(options.duration),
+        batch_size = options.batch_size,
+        rate = ?options.rate,
+        "Starting benchmark"
+    );
+    Ok(send_batches(&client, options, shutdown))
+}
+
+fn send_batches(
+    client: &OtlpClient,
+    options: &BenchmarkOptions,
+    shutdown: &AtomicBool,
+) -> BenchmarkReport {
+    let labels = HashMap::new();
+    let source_options = SourcePayloadOptions::default();
+    let mut report = BenchmarkReport::default();
+    let start = Instant::now();
+    let mut generated: u64 = 0;
+
+    while start.elapsed() < options.duration && !shutdown.load(Ordering::Relaxed) {
+        // Pace against the schedule so a slow request is caught up on later
+        if let Some(rate) = options.rate.filter(|&r| r > 0) {
+            let due = Duration::from_secs_f64(generated as f64 / rate as f64);
+            let wait = due.saturating_sub(start.elapsed());
+            if !wait.is_zero() {
+                std::thread::sleep(wait.min(options.duration.saturating_sub(start.elapsed())));
+                continue;
+            }
+        }
+
+        let entries = synthetic_batch(generated, options.batch_size);
+        generated += entries.len() as u64;
+
+        let sent = Instant::now();
+        let result = client.send(
+            BENCHMARK_SOURCE,
+            BENCHMARK_SOURCE,
+            Batch::new(&entries),
+            &labels,
+            &source_options,
+        );
+        report.latencies.push(sent.elapsed());
+        report.batches += 1;
+        match result {
+            Ok(()) => report.records += entries.len() as u64,
+            Err(e) => {
+                report.failed_batches += 1;
+                debug!(error = %e, "Benchmark request failed");
+            }
+        }
+    }
+
+    report.elapsed = start.elapsed();
+    report.latencies.sort_unstable();
+    report
+}
+
+/// `size` synthetic entries numbered from `first`.
+fn synthetic_batch(first: u64, size: usize) -> Vec<JournalEntry> {
+    let now_us = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64;
+    (first..first + size as u64)
+        .map(|seq| {
+            let unit = format!("bench-{}.service", seq as usize % SYNTHETIC_UNITS);
+            JournalEntry {
+                cursor: format!("s=benchmark;i={:x}", seq),
+                realtime_timestamp: now_us,
+                message: format!(
+                    "benchmark record {} from {}: request completed status=200 bytes=5120",
+                    seq, unit
+                ),
+                priority: Some(6),
+                syslog_identifier: Some("bench".to_string()),
+                pid: Some("4242".to_string()),
+                systemd_unit: Some(unit),
+                extra_fields: HashMap::from([("REQUEST_ID".to_string(), format!("{:016x}", seq))]),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::otlp::{OtlpTimeouts, PayloadOptions};
+
+    #[test]
+    fn test_percentile() {
+        let report = BenchmarkReport {
+            latencies: (1..=100).map(Duration::from_millis).collect(),
+            ..Default::default()
+        };
+        assert_eq!(report.percentile(50.0), Some(Duration::from_millis(50)));
+        assert_eq!(report.percentile(99.0), Some(Duration::from_millis(99)));
+        assert_eq!(report.percentile(100.0), Some(Duration::from_millis(100)));
+        assert_eq!(report.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(BenchmarkReport::default().percentile(50.0), None);
+    }
+
+    #[test]
+    fn test_send_batches_counts_results() {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for (n, stream) in listener.incoming().enumerate() {
+                let Ok(mut stream) = stream else { break };
+                let mut buf = [0u8; 65536];
+                let _ = stream.read(&mut buf);
+                // Every third request fails
+                let status = if n % 3 == 2 {
+                    "500 Internal Server Error"
+                } else {
+                    "200 OK"
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+            }
+        });
+
+        let client = OtlpClient::new(
+            &format!("http://{}", addr),
+            None,
+            None,
+            &HashMap::new(),
+            OtlpTimeouts::default(),
+            PayloadOptions::default(),
+        )
+        .unwrap();
+        let options = BenchmarkOptions {
+            duration: Duration::from_secs(60),
+            rate: None,
+            batch_size: 5,
+        };
+        let shutdown = AtomicBool::new(false);
+        let report = std::thread::scope(|scope| {
+            let sender = scope.spawn(|| send_batches(&client, &options, &shutdown));
+            while !sender.is_finished() {
+                std::thread::sleep(Duration::from_millis(50));
+                shutdown.store(true, Ordering::Relaxed);
+            }
+            sender.join().unwrap()
+        });
+
+        assert!(report.batches > 0);
+        assert_eq!(report.latencies.len() as u64, report.batches);
+        assert_eq!(
+            report.records,
+            (report.batches - report.failed_batches) * options.batch_size as u64
+        );
+    }
+
+    #[test]
+    fn test_synthetic_batch() {
+        let batch = synthetic_batch(10, 3);
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch[0].cursor, "s=benchmark;i=a");
+        assert_eq!(batch[0].systemd_unit.as_deref(), Some("bench-2.service"));
+        assert_eq!(batch[2].priority, Some(6));
+    }
+}
@@ -2,14 +2,21 @@
 //!
 //! Each source runs its own collector thread.
 
-use crate::config::{Source, TlsConfig};
-use crate::cursor::CursorManager;
-use crate::journal::{JournalClient, JournalError};
+use crate::audit::FilterAudit;
+use crate::backoff::Backoff;
+use crate::config::{
+    BodyFormat, Config, FieldMatch, PriorityFallback, RetryJitter, Source, SourceMode,
+    TimestampSource,
+};
+use crate::cursor::{CursorManager, cursor_realtime};
+use crate::journal::{JournalClient, JournalEntry, JournalError, JournalOptions, RequestSigner};
 use crate::metrics::MetricsState;
-use crate::otlp::{OtlpClient, OtlpError};
+use crate::otlp::{Batch, OtlpClient, OtlpError, SourcePayloadOptions, severity_name};
+use parking_lot::{Condvar, Mutex};
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 
@@ -28,45 +35,247 @@ pub struct Collector {
     source: Source,
     journal: JournalClient,
     otlp: Arc<OtlpClient>,
+    /// Scope name and severity map for this source's records.
+    payload: SourcePayloadOptions,
     cursor: CursorManager,
     batch_size: usize,
     metrics: Option<Arc<MetricsState>>,
+    /// Realtime timestamp (µs) of the newest entry the cursor has moved past.
+    last_entry_realtime: Option<u64>,
+    retry_jitter: RetryJitter,
+    timestamp_source: TimestampSource,
+    /// Hold short batches until full or this old; `None` forwards every poll.
+    max_batch_age: Option<Duration>,
+    /// Held entries that make a batch due before it is full.
+    min_batch_size: Option<usize>,
+    priority_fallback: PriorityFallback,
+    /// Stretch the poll interval after this many consecutive empty polls.
+    idle_after_empty_polls: Option<u32>,
+    /// Upper bound for the stretched interval.
+    max_idle_poll_interval: Duration,
+    /// Skip the backlog on startup when the saved cursor is older than this.
+    max_resume_age: Option<Duration>,
+    /// Whether the saved cursor has been checked against `max_resume_age`.
+    resume_checked: bool,
+    /// Saved cursor found too old by `max_resume_age`. It stays on disk until
+    /// the first forward replaces it, so a restart before then still skips.
+    skipped_cursor: Option<String>,
+    filter_audit: Option<Arc<FilterAudit>>,
+    /// Tag records with the gatewayd `Server` header.
+    gatewayd_version_attribute: bool,
+    /// Held for the first poll when startup is ramped.
+    startup_gate: Option<Arc<StartupGate>>,
+    /// Shared cap on fetched entries whose cursor is not yet persisted.
+    unacked: Option<Arc<UnackedBudget>>,
+    /// In-cycle resends of a batch after a transient OTLP failure.
+    otlp_max_retries: u32,
+    otlp_retry_backoff: Duration,
+    /// Raised on shutdown; set by [`run_loop`] so fetch and send retries
+    /// stop early.
+    shutdown: Arc<AtomicBool>,
+    /// Settings reloaded while running, picked up before the next poll.
+    live_updates: LiveUpdates,
+    pending: PendingBatch,
+    /// The last follow stream broke off; [`run_loop`] reconnects with its
+    /// own backoff rather than the poll backoff.
+    stream_dropped: bool,
+}
+
+/// Settings a running collector takes over from a reloaded configuration.
+#[derive(Debug, Clone)]
+pub struct LiveUpdate {
+    pub poll_interval: Duration,
+    pub labels: HashMap<String, String>,
+}
+
+/// Slot holding the latest [`LiveUpdate`] for a running collector.
+pub type LiveUpdates = Arc<Mutex<Option<LiveUpdate>>>;
+
+/// Entries fetched but not yet forwarded.
+#[derive(Debug, Default)]
+struct PendingBatch {
+    entries: Vec<JournalEntry>,
+    /// Cursor of the last fetched entry, including dropped ones.
+    cursor: Option<String>,
+    /// Realtime timestamp (µs) of the last fetched entry.
+    last_entry_realtime: Option<u64>,
+    /// When the oldest held entry was fetched.
+    since: Option<Instant>,
+    /// Fetched after a 410 cursor reset; tagged so gap/overlap windows can
+    /// be audited downstream.
+    after_cursor_reset: bool,
 }
 
 impl Collector {
     /// Create a new collector for a source
     pub fn new(
         source: Source,
-        global_tls: &Option<TlsConfig>,
+        config: &Config,
         otlp: Arc<OtlpClient>,
         cursor: CursorManager,
-        batch_size: usize,
-        max_field_bytes: usize,
         metrics: Option<Arc<MetricsState>>,
     ) -> Result<Self, CollectorError> {
-        let tls = source.effective_tls(global_tls);
+        let tls = source.effective_tls(&config.tls);
+        if tls.as_ref().is_some_and(|t| t.insecure_skip_verify) {
+            warn!(
+                source = %source.name,
+                url = %source.url,
+                "TLS certificate verification is disabled for this source"
+            );
+        }
         let journal = JournalClient::new(
             &source.url,
             source.units.clone(),
             tls.as_ref(),
-            &source.headers,
-            max_field_bytes,
+            &source
+                .request_headers()
+                .map_err(|e| JournalError::Config(e.to_string()))?,
+            JournalOptions {
+                max_field_bytes: config.max_field_bytes,
+                http2: source.http2,
+                keep_raw: config.body_format == BodyFormat::RawJson,
+                cursor_skip: source.cursor_skip,
+                max_attempts: source.fetch_max_attempts,
+                retry_backoff: source.fetch_retry_backoff,
+                retry_empty: source.retry_empty_polls,
+                retry_empty_delay: source.retry_empty_delay,
+                retry_jitter: config.retry_jitter,
+                start_timestamp: source.start_timestamp,
+                max_connections: source.max_connections,
+                format: source.format,
+                tls_server_name: source.tls_server_name.clone(),
+                field_normalization: config.field_normalization.clone(),
+                field_filter: source.field_filter.clone(),
+                follow: source.mode == SourceMode::Follow,
+                source_policy: config.source_policy.clone(),
+            },
         )?;
+        let journal = match &source.hmac {
+            Some(hmac) => journal.with_request_signer(RequestSigner::hmac_sha256(hmac)),
+            None => journal,
+        };
+        let journal = match &metrics {
+            Some(m) => {
+                let (m, name) = (m.clone(), source.name.clone());
+                journal.with_in_flight_observer(Box::new(move |n| {
+                    m.record_journal_in_flight(&name, n)
+                }))
+            }
+            None => journal,
+        };
 
         Ok(Self {
+            payload: SourcePayloadOptions::new(&source),
             source,
             journal,
             otlp,
             cursor,
-            batch_size,
+            batch_size: config.batch_size,
             metrics,
+            last_entry_realtime: None,
+            retry_jitter: config.retry_jitter,
+            timestamp_source: config.timestamp_source,
+            max_batch_age: config.max_batch_age,
+            min_batch_size: config.min_batch_size,
+            priority_fallback: config.priority_fallback,
+            idle_after_empty_polls: config.idle_after_empty_polls,
+            max_idle_poll_interval: config.max_idle_poll_interval,
+            max_resume_age: config.max_resume_age,
+            resume_checked: false,
+            skipped_cursor: None,
+            filter_audit: None,
+            gatewayd_version_attribute: config.gatewayd_version_attribute,
+            startup_gate: None,
+            unacked: None,
+            otlp_max_retries: config.otlp_max_retries,
+            otlp_retry_backoff: config.otlp_retry_backoff,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            live_updates: LiveUpdates::default(),
+            pending: PendingBatch::default(),
+            stream_dropped: false,
         })
     }
 
+    /// Slot for handing this collector reloaded settings once it is running.
+    pub fn live_updates(&self) -> LiveUpdates {
+        self.live_updates.clone()
+    }
+
+    /// Take over reloaded labels, keeping the detected gatewayd version.
+    fn apply_labels(&mut self, mut labels: HashMap<String, String>) {
+        if self.gatewayd_version_attribute
+            && let Some(version) = self.source.labels.get(GATEWAYD_VERSION_ATTRIBUTE)
+        {
+            labels.insert(GATEWAYD_VERSION_ATTRIBUTE.to_string(), version.clone());
+        }
+        self.source.labels = labels;
+    }
+
+    /// Sign every gatewayd request with `signer`, replacing the source's
+    /// `hmac` signer if it has one.
+    pub fn with_request_signer(mut self, signer: RequestSigner) -> Self {
+        self.journal = self.journal.with_request_signer(signer);
+        self
+    }
+
+    /// Wait for a slot in `gate` before the first poll.
+    pub fn with_startup_gate(mut self, gate: Arc<StartupGate>) -> Self {
+        self.startup_gate = Some(gate);
+        self
+    }
+
+    /// Count held and in-flight entries against `budget`, fetching less (or
+    /// nothing) while it is used up.
+    pub fn with_unacked_budget(mut self, budget: Arc<UnackedBudget>) -> Self {
+        self.unacked = Some(budget);
+        self
+    }
+
+    /// Report a sample of filtered entries to `audit`.
+    pub fn with_filter_audit(mut self, audit: Arc<FilterAudit>) -> Self {
+        self.filter_audit = Some(audit);
+        self
+    }
+
     /// Run a single poll cycle
+    ///
+    /// Fetched entries join the pending batch, which is forwarded once it is
+    /// due (see [`Collector::batch_due`]). Returns the number of entries
+    /// fetched.
     pub fn poll(&mut self) -> Result<usize, CollectorError> {
-        let start = std::time::Instant::now();
-        let current_cursor = self.cursor.load();
+        let start = Instant::now();
+
+        // A full pending batch left over from an earlier cycle goes out first
+        if self.pending.entries.len() >= self.batch_size {
+            self.flush(start)?;
+        }
+
+        if !self.resume_checked {
+            self.skip_stale_backlog();
+            self.resume_checked = true;
+        }
+
+        let current_cursor = self.pending.cursor.clone().or_else(|| {
+            self.cursor
+                .load()
+                .filter(|c| self.skipped_cursor.as_ref() != Some(c))
+        });
+        let mut fetch_size = self.batch_size - self.pending.entries.len();
+        if let Some(budget) = &self.unacked {
+            fetch_size = budget.try_reserve(fetch_size);
+            if fetch_size == 0 {
+                debug!(
+                    source = %self.source.name,
+                    unacked = budget.used(),
+                    "max_unacked_entries reached, skipping fetch"
+                );
+                if self.batch_due() {
+                    self.flush(start)?;
+                }
+                self.record_unacked();
+                return Ok(0);
+            }
+        }
 
         debug!(
             source = %self.source.name,
@@ -74,84 +283,237 @@ impl Collector {
             "Starting poll"
         );
 
-        // Fetch entries from journal
-        let entries = match self
-            .journal
-            .fetch(current_cursor.as_deref(), self.batch_size)
-        {
+        let entries = match self.fetch_entries(current_cursor.as_deref(), fetch_size) {
             Ok(entries) => entries,
+            Err(e) => {
+                self.release_unacked(fetch_size);
+                return Err(e);
+            }
+        };
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_batch_fill(&self.source.name, entries.len() as f64 / fetch_size as f64);
+        }
+
+        if entries.is_empty() {
+            debug!(source = %self.source.name, "No new entries");
+            self.release_unacked(fetch_size);
+            if self.batch_due() {
+                self.flush(start)?;
+            }
+            self.record_unacked();
+            if let Some(metrics) = &self.metrics {
+                metrics.record_poll(&self.source.name, start.elapsed());
+            }
+            return Ok(0);
+        }
+
+        let count = entries.len();
+        let last_cursor = entries.last().map(|e| e.cursor.clone());
+        let last_entry_realtime = entries.last().map(|e| e.realtime_timestamp);
+        let entries = self.filter_required_fields(self.filter_stale(entries));
+        let mut entries = self.filter_ids(entries);
+        self.normalize_priorities(&mut entries);
+        let entries = self.sample(entries);
+        // Dropped entries are not replayed as records, so only kept ones stay counted
+        self.release_unacked(fetch_size.saturating_sub(entries.len()));
+
+        if !entries.is_empty() && self.pending.since.is_none() {
+            self.pending.since = Some(start);
+        }
+        self.pending.entries.extend(entries);
+        self.pending.cursor = last_cursor;
+        self.pending.last_entry_realtime = last_entry_realtime;
+
+        if self.batch_due() {
+            self.flush(start)?;
+        } else {
+            debug!(
+                source = %self.source.name,
+                pending = self.pending.entries.len(),
+                "Holding partial batch"
+            );
+        }
+
+        self.record_unacked();
+        if let Some(metrics) = &self.metrics {
+            metrics.record_poll(&self.source.name, start.elapsed());
+        }
+
+        Ok(count)
+    }
+
+    /// Fetch a batch, or stream one from a `follow` request in follow mode.
+    fn fetch_journal(
+        &mut self,
+        cursor: Option<&str>,
+        fetch_size: usize,
+    ) -> Result<Vec<JournalEntry>, JournalError> {
+        match self.source.mode {
+            SourceMode::Poll => self.journal.fetch(cursor, fetch_size),
+            SourceMode::Follow => {
+                let followed = self.journal.follow(cursor, fetch_size, &self.shutdown)?;
+                self.stream_dropped = followed.dropped;
+                if followed.dropped
+                    && let Some(metrics) = &self.metrics
+                {
+                    metrics.record_stream_reconnect(&self.source.name);
+                }
+                Ok(followed.entries)
+            }
+        }
+    }
+
+    /// Fetch up to `fetch_size` entries after `cursor`, resetting to the
+    /// current boot if gatewayd no longer knows the cursor.
+    fn fetch_entries(
+        &mut self,
+        cursor: Option<&str>,
+        fetch_size: usize,
+    ) -> Result<Vec<JournalEntry>, CollectorError> {
+        let fetched = self.fetch_journal(cursor, fetch_size);
+        self.record_journal_retries();
+        self.observe_gatewayd_version();
+        match fetched {
+            Ok(entries) => Ok(entries),
             Err(JournalError::InvalidCursor) => {
                 warn!(
                     source = %self.source.name,
                     "Cursor invalid (410 Gone), resetting to current boot"
                 );
                 self.cursor.reset()?;
+                self.pending.cursor = None;
+                self.pending.after_cursor_reset = true;
+                // A start point set by skip_stale_backlog only applies to
+                // the first fetch after startup, not to this reset
+                self.journal
+                    .set_start_timestamp(self.source.start_timestamp);
 
                 if let Some(metrics) = &self.metrics {
                     metrics.record_error(&self.source.name, "invalid_cursor");
                 }
 
                 // Retry with no cursor (current boot)
-                self.journal.fetch(None, self.batch_size)?
+                let fetched = self.fetch_journal(None, fetch_size);
+                self.record_journal_retries();
+                self.observe_gatewayd_version();
+                Ok(fetched?)
             }
             Err(e) => {
+                if let JournalError::Unauthorized { status } = &e {
+                    error!(
+                        source = %self.source.name,
+                        url = %self.source.url,
+                        status = %status,
+                        "gatewayd rejected the source's credentials; check username/password, bearer_token_file or headers"
+                    );
+                }
                 if let Some(metrics) = &self.metrics {
                     let error_type = match &e {
                         JournalError::Http(_) => "http",
-                        JournalError::Json(_) => "parse",
+                        JournalError::Json(_) | JournalError::Export(_) => "parse",
                         JournalError::ServerError { .. } => "server",
+                        JournalError::Unauthorized { .. } => "unauthorized",
                         JournalError::InvalidCursor => "invalid_cursor",
                         JournalError::Config(_) => "config",
                     };
                     metrics.record_error(&self.source.name, error_type);
                 }
-                return Err(e.into());
+                Err(e.into())
             }
+        }
+    }
+
+    /// Whether the pending batch should be forwarded now: always without
+    /// `max_batch_age`, otherwise once it holds `min_batch_size` entries
+    /// (default: a full `batch_size`) or its oldest entry has been held for
+    /// `max_batch_age`.
+    fn batch_due(&self) -> bool {
+        let Some(max_age) = self.max_batch_age else {
+            return true;
         };
+        self.pending.entries.len() >= self.min_batch_size.unwrap_or(self.batch_size)
+            || self
+                .pending
+                .since
+                .is_none_or(|since| since.elapsed() >= max_age)
+    }
 
-        if entries.is_empty() {
-            debug!(source = %self.source.name, "No new entries");
+    /// Forward any entries held for batch assembly and persist the cursor
+    /// past them. `run_loop` calls this once shutdown is observed.
+    pub fn flush_pending(&mut self) -> Result<(), CollectorError> {
+        self.flush(Instant::now())
+    }
+
+    /// Forward the pending batch and advance the cursor past it.
+    ///
+    /// On OTLP failure the batch is discarded without moving the cursor, so
+    /// the next poll re-fetches it, unless a 410 reset left it without a
+    /// cursor; then it stays pending. Discarded or forwarded entries stop
+    /// counting against `max_unacked_entries`.
+    fn flush(&mut self, start: Instant) -> Result<(), CollectorError> {
+        let pending = std::mem::take(&mut self.pending);
+        let held = pending.entries.len();
+        let result = self.forward(pending, start);
+        self.release_unacked(held - self.pending.entries.len());
+        result
+    }
+
+    fn forward(&mut self, pending: PendingBatch, start: Instant) -> Result<(), CollectorError> {
+        if pending.entries.is_empty() {
+            let Some(last_cursor) = pending.cursor else {
+                return Ok(());
+            };
+            // Everything was intentionally dropped; move the cursor past it.
+            // Records forwarded next are still the first after a reset.
+            self.pending.after_cursor_reset |= pending.after_cursor_reset;
+            self.advance_cursor(&last_cursor)?;
+            self.last_entry_realtime = pending.last_entry_realtime;
             if let Some(metrics) = &self.metrics {
-                metrics.record_poll(&self.source.name, start.elapsed());
+                metrics.record_last_entry(&self.source.name, pending.last_entry_realtime);
             }
-            return Ok(0);
+            debug!(
+                source = %self.source.name,
+                "All fetched entries dropped, cursor advanced"
+            );
+            return Ok(());
         }
 
-        let count = entries.len();
-        let last_cursor = entries.last().map(|e| e.cursor.clone());
+        let forwarded = pending.entries.len();
 
         debug!(
             source = %self.source.name,
-            count = count,
-            "Fetched entries, forwarding to OTLP"
+            count = forwarded,
+            "Forwarding batch to OTLP"
         );
 
         // Forward to OTLP
-        match self
-            .otlp
-            .send(&self.source.name, &entries, &self.source.labels)
-        {
+        let batch = Batch {
+            entries: &pending.entries,
+            after_cursor_reset: pending.after_cursor_reset,
+        };
+        match self.send_with_retries(batch) {
             Ok(()) => {
-                // Only advance cursor after successful OTLP push
-                if let Some(cursor) = last_cursor {
-                    self.cursor.save(&cursor)?;
+                // Only advance cursor after successful OTLP push. Entries
+                // held across a 410 reset have no cursor left to advance.
+                if let Some(last_cursor) = &pending.cursor {
+                    self.advance_cursor(last_cursor)?;
                 }
+                self.last_entry_realtime = pending.last_entry_realtime;
 
-                let last_entry_realtime = entries.last().map(|e| e.realtime_timestamp);
                 if let Some(metrics) = &self.metrics {
-                    metrics.record_forwarded(&self.source.name, count as u64);
-                    metrics.record_poll(&self.source.name, start.elapsed());
-                    metrics.record_last_entry(&self.source.name, last_entry_realtime);
+                    metrics.record_forwarded(&self.source.name, forwarded as u64);
+                    metrics.record_last_entry(&self.source.name, pending.last_entry_realtime);
                 }
 
                 info!(
                     source = %self.source.name,
-                    count = count,
+                    count = forwarded,
                     duration_ms = start.elapsed().as_millis(),
                     "Forwarded entries"
                 );
 
-                Ok(count)
+                Ok(())
             }
             Err(e) => {
                 // Do NOT advance cursor on OTLP failure
@@ -164,28 +526,569 @@ impl Collector {
                 if let Some(metrics) = &self.metrics {
                     metrics.record_error(&self.source.name, "otlp");
                 }
+                if pending.cursor.is_none() {
+                    // Held across a 410 reset, so the old cursor cannot
+                    // fetch them again; keep them for the next flush
+                    self.pending = pending;
+                } else {
+                    // The batch will be re-fetched; keep it tagged
+                    self.pending.after_cursor_reset |= pending.after_cursor_reset;
+                }
 
                 Err(e.into())
             }
         }
     }
 
+    /// Send `batch`, resending it up to `otlp_max_retries` times with
+    /// backoff after transport errors and 5xx responses. Shutdown cuts the
+    /// retries short and returns the last error.
+    fn send_with_retries(&self, batch: Batch<'_>) -> Result<(), OtlpError> {
+        let mut backoff = Backoff::new(
+            self.otlp_retry_backoff,
+            MAX_OTLP_RETRY_DELAY,
+            self.retry_jitter,
+        );
+        let mut attempt = 0;
+        loop {
+            let error = match self.otlp.send(
+                &self.source.name,
+                self.source.host_name(),
+                batch,
+                &self.source.labels,
+                &self.payload,
+            ) {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            if attempt >= self.otlp_max_retries
+                || !error.is_transient()
+                || self.shutdown.load(Ordering::Relaxed)
+            {
+                return Err(error);
+            }
+
+            attempt += 1;
+            let delay = backoff.delay(attempt);
+            debug!(
+                source = %self.source.name,
+                attempt,
+                max_retries = self.otlp_max_retries,
+                retry_in_ms = delay.as_millis(),
+                error = %error,
+                "Retrying OTLP send"
+            );
+            if let Some(metrics) = &self.metrics {
+                metrics.record_otlp_retry(&self.source.name);
+            }
+            let mut left = delay;
+            while left > Duration::ZERO {
+                if self.shutdown.load(Ordering::Relaxed) {
+                    return Err(error);
+                }
+                let sleep = left.min(Duration::from_millis(100));
+                std::thread::sleep(sleep);
+                left = left.saturating_sub(sleep);
+            }
+        }
+    }
+
+    /// Persist a new cursor position and mark forward progress.
+    fn advance_cursor(&self, cursor: &str) -> Result<(), CollectorError> {
+        self.cursor.save(cursor)?;
+        if let Some(metrics) = &self.metrics {
+            metrics.record_cursor_advance(&self.source.name);
+        }
+        Ok(())
+    }
+
+    /// Forward any entries held for batch assembly, so their cursor is on
+    /// disk, and log a status snapshot.
+    fn report_status(&mut self, processed: u64) {
+        let held = self.pending.entries.len();
+        if held > 0 {
+            info!(source = %self.source.name, entries = held, "Flushing held entries on status request");
+            if let Err(e) = self.flush_pending() {
+                warn!(source = %self.source.name, error = %e, "Failed to flush held entries");
+            }
+        }
+
+        let lag_s = self
+            .last_entry_realtime
+            .map(|us| (current_unix_ms() as f64 / 1000.0 - us as f64 / 1_000_000.0).max(0.0));
+        info!(
+            source = %self.source.name,
+            cursor = ?self.cursor.load(),
+            pending = self.pending.entries.len(),
+            pending_cursor = ?self.pending.cursor,
+            lag_s = ?lag_s,
+            entries = processed,
+            "Collector status"
+        );
+    }
+
+    fn release_unacked(&self, count: usize) {
+        if let Some(budget) = &self.unacked {
+            budget.release(count);
+        }
+    }
+
+    fn record_unacked(&self) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_unacked(&self.source.name, self.pending.entries.len());
+        }
+    }
+
+    fn record_journal_retries(&self) {
+        let retries = self.journal.take_retries();
+        if retries > 0
+            && let Some(metrics) = &self.metrics
+        {
+            metrics.record_journal_retries(&self.source.name, retries);
+        }
+    }
+
+    /// Pick up a changed gatewayd `Server` header for the version metric
+    /// and, if enabled, the resource attribute.
+    fn observe_gatewayd_version(&mut self) {
+        let Some(version) = self.journal.take_server_version_change() else {
+            return;
+        };
+        info!(
+            source = %self.source.name,
+            version = %version,
+            "gatewayd version detected"
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.set_gatewayd_version(&self.source.name, &version);
+        }
+        if self.gatewayd_version_attribute {
+            self.source
+                .labels
+                .insert(GATEWAYD_VERSION_ATTRIBUTE.to_string(), version);
+        }
+    }
+
+    /// Drop entries whose event time is older than the source's `max_entry_age`.
+    fn filter_stale(&self, entries: Vec<JournalEntry>) -> Vec<JournalEntry> {
+        let Some(max_age) = self.source.max_entry_age else {
+            return entries;
+        };
+
+        let cutoff_us = (current_unix_ms() * 1000).saturating_sub(max_age.as_micros() as u64);
+        let (kept, dropped): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|entry| event_time_us(entry, self.timestamp_source) >= cutoff_us);
+
+        if !dropped.is_empty() {
+            debug!(source = %self.source.name, count = dropped.len(), "Dropped stale entries");
+        }
+        self.record_drops("stale", &dropped);
+        kept
+    }
+
+    /// If the saved cursor is older than `max_resume_age`, ignore it and start
+    /// from now instead of replaying the backlog. The cursor's age is its
+    /// entry time (`t=`), or the cursor file's mtime when it has none.
+    fn skip_stale_backlog(&mut self) {
+        let Some(max_age) = self.max_resume_age else {
+            return;
+        };
+        let Some(cursor) = self.cursor.load() else {
+            return;
+        };
+        let Some(resume_point) = cursor_realtime(&cursor).or_else(|| self.cursor.saved_at()) else {
+            return;
+        };
+        let now = SystemTime::now();
+        let age = now.duration_since(resume_point).unwrap_or_default();
+        if age <= max_age {
+            return;
+        }
+
+        warn!(
+            source = %self.source.name,
+            gap_start = %humantime::format_rfc3339_seconds(resume_point),
+            gap_end = %humantime::format_rfc3339_seconds(now),
+            age = %humantime::format_duration(Duration::from_secs(age.as_secs())),
+            max_resume_age = %humantime::format_duration(max_age),
+            "Saved cursor is older than max_resume_age; skipping backlog, entries in the gap are not forwarded"
+        );
+        self.skipped_cursor = Some(cursor);
+        self.journal.set_start_timestamp(Some(now));
+        if let Some(metrics) = &self.metrics {
+            metrics.record_resume_skipped(&self.source.name);
+        }
+    }
+
+    /// Drop entries missing the source's `require_fields`.
+    fn filter_required_fields(&self, entries: Vec<JournalEntry>) -> Vec<JournalEntry> {
+        if self.source.require_fields.is_empty() {
+            return entries;
+        }
+
+        let (kept, dropped): (Vec<_>, Vec<_>) = entries.into_iter().partition(|entry| {
+            has_required_fields(
+                entry,
+                &self.source.require_fields,
+                self.source.require_fields_match,
+            )
+        });
+        self.record_drops("missing_field", &dropped);
+        kept
+    }
+
+    /// Drop entries whose `_UID`/`_GID` fail the source's ID filters.
+    fn filter_ids(&self, entries: Vec<JournalEntry>) -> Vec<JournalEntry> {
+        let mut entries = entries;
+        for (field, reason, filter) in [
+            ("_UID", "uid_filter", &self.source.uid_filter),
+            ("_GID", "gid_filter", &self.source.gid_filter),
+        ] {
+            let Some(filter) = filter else { continue };
+            let (kept, dropped): (Vec<_>, Vec<_>) = entries
+                .into_iter()
+                .partition(|entry| filter.keeps(entry.field(field)));
+            self.record_drops(reason, &dropped);
+            entries = kept;
+        }
+        entries
+    }
+
+    /// Count entries dropped by a filter and pass a sample to the filter audit.
+    fn record_drops(&self, reason: &'static str, dropped: &[JournalEntry]) {
+        if dropped.is_empty() {
+            return;
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_dropped(&self.source.name, reason, dropped.len() as u64);
+        }
+        if let Some(audit) = &self.filter_audit {
+            audit.sample(&self.source.name, reason, dropped);
+        }
+    }
+
+    /// Drop entries according to the source's per-severity sample rates.
+    /// Sampled-out entries are intentionally lost; the cursor still advances.
+    fn sample(&self, entries: Vec<JournalEntry>) -> Vec<JournalEntry> {
+        let Some(sampling) = &self.source.sampling else {
+            return entries;
+        };
+
+        let mut dropped: HashMap<&'static str, u64> = HashMap::new();
+        let (kept, sampled_out): (Vec<_>, Vec<_>) = entries.into_iter().partition(|entry| {
+            let severity = severity_name(self.payload.severity_map.get(entry.priority).0);
+            let keep = sample_keep(&entry.cursor, sampling.rate(severity));
+            if !keep {
+                *dropped.entry(severity).or_default() += 1;
+            }
+            keep
+        });
+
+        if let Some(metrics) = &self.metrics {
+            for (severity, count) in dropped {
+                metrics.record_sampled_out(&self.source.name, &severity.to_lowercase(), count);
+            }
+        }
+        if let Some(audit) = &self.filter_audit
+            && !sampled_out.is_empty()
+        {
+            audit.sample(&self.source.name, "sampled", &sampled_out);
+        }
+
+        kept
+    }
+
+    /// Apply `priority_fallback` to entries with a `PRIORITY` outside 0-7,
+    /// logging and counting them so the offending source can be found.
+    fn normalize_priorities(&self, entries: &mut [JournalEntry]) {
+        let mut count = 0u64;
+        let mut example = None;
+        for entry in entries.iter_mut() {
+            if normalize_priority(entry, self.priority_fallback) {
+                count += 1;
+                example.get_or_insert_with(|| entry.cursor.clone());
+            }
+        }
+        if count == 0 {
+            return;
+        }
+
+        warn!(
+            source = %self.source.name,
+            count,
+            cursor = example.as_deref().unwrap_or_default(),
+            fallback = ?self.priority_fallback,
+            "Entries with PRIORITY outside 0-7"
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.record_invalid_priority(&self.source.name, count);
+        }
+    }
+
+    /// Probe gatewayd until it accepts connections, for at most `timeout`.
+    ///
+    /// Returns false if the wait expired (or shutdown was requested) first.
+    pub fn wait_until_reachable(&self, timeout: Duration, shutdown: &AtomicBool) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut backoff = Backoff::new(
+            STARTUP_PROBE_INTERVAL,
+            MAX_STARTUP_PROBE_INTERVAL,
+            self.retry_jitter,
+        );
+        let mut attempt = 1;
+        loop {
+            let error = match self.journal.probe() {
+                Ok(()) => {
+                    if attempt > 1 {
+                        info!(source = %self.source.name, attempt, "gatewayd reachable");
+                    }
+                    return true;
+                }
+                Err(e) => e,
+            };
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || shutdown.load(Ordering::Relaxed) {
+                error!(
+                    source = %self.source.name,
+                    url = %self.source.url,
+                    error = %error,
+                    "gatewayd still unreachable after startup wait"
+                );
+                return false;
+            }
+
+            let delay = backoff.delay(attempt).min(remaining);
+            info!(
+                source = %self.source.name,
+                attempt,
+                retry_in_ms = delay.as_millis(),
+                remaining_s = remaining.as_secs(),
+                error = %error,
+                "Waiting for gatewayd to become reachable"
+            );
+            let mut left = delay;
+            while left > Duration::ZERO && !shutdown.load(Ordering::Relaxed) {
+                let sleep = left.min(Duration::from_millis(100));
+                std::thread::sleep(sleep);
+                left = left.saturating_sub(sleep);
+            }
+            attempt += 1;
+        }
+    }
+
     /// Get source name
     pub fn source_name(&self) -> &str {
         &self.source.name
     }
 }
 
+/// Replace an out-of-range priority per `fallback`; returns whether the
+/// entry's `PRIORITY` was out of range.
+fn normalize_priority(entry: &mut JournalEntry, fallback: PriorityFallback) -> bool {
+    if !entry.priority_out_of_range() {
+        return false;
+    }
+    entry.priority = fallback.resolve(entry.priority);
+    true
+}
+
+/// Whether `entry` carries the required fields under the given match mode.
+fn has_required_fields(entry: &JournalEntry, fields: &[String], mode: FieldMatch) -> bool {
+    let mut present = fields.iter().map(|f| entry.field(f).is_some());
+    match mode {
+        FieldMatch::All => present.all(|p| p),
+        FieldMatch::Any => present.any(|p| p),
+    }
+}
+
+/// Event time (µs) as it will be sent, per the configured timestamp source.
+fn event_time_us(entry: &JournalEntry, timestamp_source: TimestampSource) -> u64 {
+    match timestamp_source {
+        TimestampSource::Source => entry
+            .source_realtime_timestamp()
+            .unwrap_or(entry.realtime_timestamp),
+        TimestampSource::Journald => entry.realtime_timestamp,
+    }
+}
+
+/// Deterministically decide whether an entry survives sampling at `rate`.
+///
+/// Keyed on the cursor with FNV-1a (plus a murmur3 finalizer to spread the
+/// high bits) so the decision is stable across runs and builds, making a
+/// replayed batch sample the same way.
+fn sample_keep(cursor: &str, rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in cursor.bytes() {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^= hash >> 33;
+    (hash as f64 / u64::MAX as f64) < rate
+}
+
+/// Resource attribute carrying the gatewayd `Server` header.
+const GATEWAYD_VERSION_ATTRIBUTE: &str = "systemd.gatewayd.version";
 const MAX_DRAIN_BATCHES: u32 = 100;
 const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// Delay before the second consecutive reconnect of a dropped follow
+/// stream (the first is immediate); doubles up to the max.
+const STREAM_RECONNECT_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_STREAM_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+/// Upper bound on a single OTLP send retry delay.
+const MAX_OTLP_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// First delay between startup reachability probes; doubles up to the max.
+const STARTUP_PROBE_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_STARTUP_PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Global cap on entries fetched but not yet covered by a persisted cursor
+/// (held for batch assembly or being sent), which bounds both memory and
+/// what a crash can replay. Collectors reserve before fetching.
+#[derive(Debug)]
+pub struct UnackedBudget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl UnackedBudget {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Entries currently counted.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Reserve up to `wanted` entries, returning how many were granted.
+    fn try_reserve(&self, wanted: usize) -> usize {
+        let mut granted = 0;
+        let _ = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                granted = wanted.min(self.limit.saturating_sub(used));
+                Some(used + granted)
+            });
+        granted
+    }
+
+    fn release(&self, count: usize) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                Some(used.saturating_sub(count))
+            });
+    }
+}
+
+/// How often a collector waiting on the [`StartupGate`] refreshes its
+/// watchdog tick and checks for shutdown.
+const STARTUP_GATE_RECHECK: Duration = Duration::from_millis(500);
+
+/// Limits how many collectors run their first poll at once, so a cold start
+/// with many sources ramps up instead of hitting every gatewayd (and DNS)
+/// together.
+pub struct StartupGate {
+    limit: usize,
+    total: usize,
+    /// Collectors currently in their first poll, and those done with it.
+    state: Mutex<(usize, usize)>,
+    slot_free: Condvar,
+}
+
+impl StartupGate {
+    pub fn new(limit: usize, total: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            total,
+            state: Mutex::new((0, 0)),
+            slot_free: Condvar::new(),
+        }
+    }
+
+    /// Block until a slot is free, keeping `tick` fresh meanwhile. Returns
+    /// `false` without a slot if shutdown is raised first.
+    fn acquire(&self, shutdown: &AtomicBool, tick: &AtomicU64) -> bool {
+        let mut state = self.state.lock();
+        while state.0 >= self.limit {
+            if shutdown.load(Ordering::Relaxed) {
+                return false;
+            }
+            tick.store(current_unix_ms(), Ordering::Relaxed);
+            self.slot_free.wait_for(&mut state, STARTUP_GATE_RECHECK);
+        }
+        state.0 += 1;
+        true
+    }
+
+    /// Free the slot taken by `source` and log the ramp's progress.
+    fn release(&self, source: &str) {
+        let mut state = self.state.lock();
+        state.0 -= 1;
+        state.1 += 1;
+        info!(
+            source = %source,
+            started = state.1,
+            total = self.total,
+            "Startup progress"
+        );
+        self.slot_free.notify_one();
+    }
+}
 
-/// Compute the next sleep duration after `consecutive_failures` failures.
-fn backoff_delay(base: Duration, failures: u32) -> Duration {
+impl std::fmt::Debug for StartupGate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StartupGate")
+            .field("limit", &self.limit)
+            .field("total", &self.total)
+            .finish()
+    }
+}
+
+/// Compute the next sleep duration after `failures` consecutive failures.
+/// `backoff` must be built with the poll interval as its base.
+fn backoff_delay(backoff: &mut Backoff, base: Duration, failures: u32) -> Duration {
     if failures == 0 {
+        backoff.reset();
         return base;
     }
-    let factor = 2u32.saturating_pow(failures.min(8));
-    base.saturating_mul(factor).min(MAX_BACKOFF)
+    backoff.delay(failures.min(8) + 1)
+}
+
+/// Delay before reopening a follow stream after `drops` consecutive drops:
+/// none after the first, then backing off.
+fn reconnect_delay(backoff: &mut Backoff, drops: u32) -> Duration {
+    if drops <= 1 {
+        backoff.reset();
+        return Duration::ZERO;
+    }
+    backoff.delay(drops - 1)
+}
+
+/// Poll interval after `empty_polls` consecutive empty polls: `base` until
+/// `after` is reached, then doubling per further empty poll up to `max`.
+fn idle_delay(base: Duration, max: Duration, empty_polls: u32, after: Option<u32>) -> Duration {
+    match after {
+        Some(after) if empty_polls >= after => {
+            let doublings = (empty_polls - after + 1).min(16);
+            base.saturating_mul(1 << doublings).min(max).max(base)
+        }
+        _ => base,
+    }
 }
 
 /// Run collector in a loop until shutdown signal.
@@ -194,15 +1097,45 @@ fn backoff_delay(base: Duration, failures: u32) -> Duration {
 /// is reached (source caught up), so the per-cycle cap never leaves data behind.
 pub fn run_loop(
     mut collector: Collector,
-    poll_interval: Duration,
+    mut poll_interval: Duration,
     shutdown: Arc<AtomicBool>,
     once: bool,
     tick: Arc<AtomicU64>,
+    status_requests: Arc<AtomicU64>,
+    polled_ok: Arc<AtomicBool>,
 ) {
     let source_name = collector.source_name().to_string();
     info!(source = %source_name, "Collector started");
+    collector.shutdown = shutdown.clone();
+    collector.journal.set_shutdown(shutdown.clone());
+
+    // Measure time-since-advance from startup until the cursor first moves
+    if let Some(metrics) = &collector.metrics {
+        metrics.record_cursor_advance(&source_name);
+    }
+
+    // Each SIGUSR1 bumps the shared counter; every collector answers once.
+    let mut status_seen = status_requests.load(Ordering::Relaxed);
 
     let mut consecutive_failures: u32 = 0;
+    let mut consecutive_empty: u32 = 0;
+    let mut backoff = Backoff::new(poll_interval, MAX_BACKOFF, collector.retry_jitter);
+    let mut stream_drops: u32 = 0;
+    let mut reconnect_backoff = Backoff::new(
+        STREAM_RECONNECT_BACKOFF,
+        MAX_STREAM_RECONNECT_DELAY,
+        collector.retry_jitter,
+    );
+    let mut processed: u64 = 0;
+    let mut effective_interval: Option<Duration> = None;
+
+    // The first poll waits for a startup slot when the ramp is limited
+    let mut startup_gate = collector.startup_gate.take();
+    if let Some(gate) = &startup_gate
+        && !gate.acquire(&shutdown, &tick)
+    {
+        startup_gate = None;
+    }
 
     loop {
         if shutdown.load(Ordering::Relaxed) {
@@ -210,14 +1143,40 @@ pub fn run_loop(
             break;
         }
 
+        let update = collector.live_updates.lock().take();
+        if let Some(update) = update {
+            info!(
+                source = %source_name,
+                poll_interval_ms = update.poll_interval.as_millis(),
+                "Applying reloaded settings"
+            );
+            if update.poll_interval != poll_interval {
+                poll_interval = update.poll_interval;
+                backoff = Backoff::new(poll_interval, MAX_BACKOFF, collector.retry_jitter);
+            }
+            collector.apply_labels(update.labels);
+        }
+
         let result = drain_cycle(&mut collector, MAX_DRAIN_BATCHES, shutdown.clone());
+        if let Some(gate) = startup_gate.take() {
+            gate.release(&source_name);
+        }
+        if result.is_ok() {
+            polled_ok.store(true, Ordering::Relaxed);
+        }
         match &result {
             Ok(0) => {
                 consecutive_failures = 0;
+                // Held entries must still be flushed within max_batch_age
+                if collector.pending.entries.is_empty() {
+                    consecutive_empty = consecutive_empty.saturating_add(1);
+                }
                 debug!(source = %source_name, "No new entries");
             }
             Ok(n) => {
                 consecutive_failures = 0;
+                consecutive_empty = 0;
+                processed += *n as u64;
                 debug!(source = %source_name, count = n, "Drain cycle completed");
             }
             Err(e) => {
@@ -227,6 +1186,12 @@ pub fn run_loop(
         }
 
         tick.store(current_unix_ms(), Ordering::Relaxed);
+        check_status_request(
+            &mut collector,
+            &status_requests,
+            &mut status_seen,
+            processed,
+        );
 
         if once {
             match &result {
@@ -246,14 +1211,92 @@ pub fn run_loop(
             }
         }
 
-        let delay = backoff_delay(poll_interval, consecutive_failures);
+        let stream_dropped = std::mem::take(&mut collector.stream_dropped);
+        let delay = if let Err(CollectorError::Otlp(OtlpError::Throttled { retry_after })) = &result
+        {
+            // The backend asked for this wait; don't poll (or back off) sooner
+            info!(
+                source = %source_name,
+                retry_after_ms = retry_after.as_millis(),
+                "OTLP endpoint throttled, waiting before next poll"
+            );
+            (*retry_after).min(MAX_BACKOFF)
+        } else if consecutive_failures > 0 {
+            backoff_delay(&mut backoff, poll_interval, consecutive_failures)
+        } else if stream_dropped {
+            // Resume from the saved cursor right away, backing off only if
+            // the stream keeps dropping
+            backoff.reset();
+            stream_drops = stream_drops.saturating_add(1);
+            info!(
+                source = %source_name,
+                consecutive = stream_drops,
+                "Follow stream dropped, reconnecting"
+            );
+            reconnect_delay(&mut reconnect_backoff, stream_drops)
+        } else if collector.source.mode == SourceMode::Follow {
+            // The follow request itself waits for new entries
+            backoff.reset();
+            stream_drops = 0;
+            Duration::ZERO
+        } else {
+            backoff.reset();
+            idle_delay(
+                poll_interval,
+                collector.max_idle_poll_interval,
+                consecutive_empty,
+                collector.idle_after_empty_polls,
+            )
+        };
+        if effective_interval != Some(delay) {
+            effective_interval = Some(delay);
+            if let Some(metrics) = &collector.metrics {
+                metrics.record_effective_interval(&source_name, delay);
+            }
+        }
         let mut remaining = delay;
         while remaining > Duration::ZERO && !shutdown.load(Ordering::Relaxed) {
             let sleep = remaining.min(Duration::from_millis(100));
             std::thread::sleep(sleep);
             remaining = remaining.saturating_sub(sleep);
+            check_status_request(
+                &mut collector,
+                &status_requests,
+                &mut status_seen,
+                processed,
+            );
         }
     }
+
+    // Forward anything still held for batch assembly
+    let held = collector.pending.entries.len();
+    if held > 0 {
+        info!(source = %source_name, entries = held, "Flushing held entries before stop");
+    }
+    if let Err(e) = collector.flush_pending() {
+        warn!(source = %source_name, error = %e, "Failed to forward pending batch on stop");
+    }
+
+    info!(
+        source = %source_name,
+        entries = processed,
+        cursor = ?collector.cursor.load(),
+        "Collector stopped"
+    );
+}
+
+/// Report status if a new request arrived since `seen`.
+fn check_status_request(
+    collector: &mut Collector,
+    status_requests: &AtomicU64,
+    seen: &mut u64,
+    processed: u64,
+) {
+    let requested = status_requests.load(Ordering::Relaxed);
+    if requested != *seen {
+        *seen = requested;
+        collector.report_status(processed);
+    }
 }
 
 fn current_unix_ms() -> u64 {
@@ -271,12 +1314,26 @@ fn drain_cycle(
     shutdown: Arc<AtomicBool>,
 ) -> Result<usize, CollectorError> {
     let mut total = 0usize;
+    let mut previous_full = false;
     for i in 0..max_batches {
         if shutdown.load(Ordering::Relaxed) {
             break;
         }
         let count = collector.poll()?;
         total += count;
+        if count == 0 && previous_full && collector.source.mode == SourceMode::Poll {
+            // A full batch suggested more pending data; an empty reply right
+            // after it may mean gatewayd returned partial results.
+            warn!(
+                source = %collector.source.name,
+                batch = i + 1,
+                "Empty fetch immediately after a full batch"
+            );
+            if let Some(metrics) = &collector.metrics {
+                metrics.record_unexpected_empty(&collector.source.name);
+            }
+        }
+        previous_full = count >= collector.batch_size;
         if count == 0 || count < collector.batch_size {
             // Short batch means we're caught up (or empty); don't burn cycles.
             break;
@@ -294,23 +1351,430 @@ fn drain_cycle(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::otlp::map_priority;
+    use crate::test_util::serve;
 
     #[test]
     fn test_backoff_delay() {
         let base = Duration::from_secs(5);
-        assert_eq!(backoff_delay(base, 0), base);
-        assert_eq!(backoff_delay(base, 1), Duration::from_secs(10));
-        assert_eq!(backoff_delay(base, 2), Duration::from_secs(20));
+        let mut backoff = Backoff::new(base, MAX_BACKOFF, RetryJitter::None);
+        assert_eq!(backoff_delay(&mut backoff, base, 0), base);
         assert_eq!(
-            backoff_delay(base, 8),
+            backoff_delay(&mut backoff, base, 1),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            backoff_delay(&mut backoff, base, 2),
+            Duration::from_secs(20)
+        );
+        assert_eq!(
+            backoff_delay(&mut backoff, base, 8),
             Duration::from_secs(300).min(Duration::from_secs(1280))
         );
-        assert_eq!(backoff_delay(base, 100), Duration::from_secs(300));
+        assert_eq!(
+            backoff_delay(&mut backoff, base, 100),
+            Duration::from_secs(300)
+        );
+    }
+
+    #[test]
+    fn test_normalize_priority() {
+        let entry = |priority: Option<u8>, raw: Option<&str>| JournalEntry {
+            priority,
+            extra_fields: raw
+                .map(|r| HashMap::from([("PRIORITY".to_string(), r.to_string())]))
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+
+        let mut valid = entry(Some(6), None);
+        assert!(!normalize_priority(&mut valid, PriorityFallback::Info));
+        assert_eq!(valid.priority, Some(6));
+
+        let mut missing = entry(None, None);
+        assert!(!normalize_priority(&mut missing, PriorityFallback::Info));
+        assert_eq!(missing.priority, None);
+
+        let mut high = entry(Some(8), None);
+        assert!(normalize_priority(&mut high, PriorityFallback::Clamp));
+        assert_eq!(high.priority, Some(7));
+
+        let mut high = entry(Some(8), None);
+        assert!(normalize_priority(&mut high, PriorityFallback::Unspecified));
+        assert_eq!(map_priority(high.priority), (0, "UNSPECIFIED"));
+
+        let mut empty = entry(None, Some(""));
+        assert!(normalize_priority(&mut empty, PriorityFallback::Clamp));
+        assert_eq!(empty.priority, None);
+
+        let mut empty = entry(None, Some(""));
+        assert!(normalize_priority(&mut empty, PriorityFallback::Info));
+        assert_eq!(map_priority(empty.priority), (9, "INFO"));
+    }
+
+    #[test]
+    fn test_has_required_fields() {
+        let entry = JournalEntry {
+            syslog_identifier: Some("app".to_string()),
+            extra_fields: HashMap::from([("TRACE_ID".to_string(), "abc".to_string())]),
+            ..Default::default()
+        };
+        let both = vec!["TRACE_ID".to_string(), "SPAN_ID".to_string()];
+        assert!(!has_required_fields(&entry, &both, FieldMatch::All));
+        assert!(has_required_fields(&entry, &both, FieldMatch::Any));
+
+        let present = vec!["TRACE_ID".to_string(), "SYSLOG_IDENTIFIER".to_string()];
+        assert!(has_required_fields(&entry, &present, FieldMatch::All));
+    }
+
+    #[test]
+    fn test_event_time_us() {
+        let entry = JournalEntry {
+            realtime_timestamp: 2_000,
+            extra_fields: HashMap::from([(
+                "_SOURCE_REALTIME_TIMESTAMP".to_string(),
+                "1000".to_string(),
+            )]),
+            ..Default::default()
+        };
+        assert_eq!(event_time_us(&entry, TimestampSource::Journald), 2_000);
+        assert_eq!(event_time_us(&entry, TimestampSource::Source), 1_000);
+        let entry = JournalEntry {
+            realtime_timestamp: 2_000,
+            ..Default::default()
+        };
+        assert_eq!(event_time_us(&entry, TimestampSource::Source), 2_000);
+    }
+
+    #[test]
+    fn test_sample_keep_is_deterministic() {
+        let cursor = "s=abc;i=42";
+        assert!(sample_keep(cursor, 1.0));
+        assert!(!sample_keep(cursor, 0.0));
+        assert_eq!(sample_keep(cursor, 0.5), sample_keep(cursor, 0.5));
+
+        let kept = (0..10_000)
+            .filter(|i| sample_keep(&format!("s=abc;i={:x}", i), 0.1))
+            .count();
+        assert!((800..1200).contains(&kept), "kept {} of 10000", kept);
+    }
+
+    #[test]
+    fn test_startup_gate_limits_concurrency() {
+        let gate = StartupGate::new(2, 3);
+        let (shutdown, tick) = (AtomicBool::new(false), AtomicU64::new(0));
+        assert!(gate.acquire(&shutdown, &tick));
+        assert!(gate.acquire(&shutdown, &tick));
+
+        std::thread::scope(|scope| {
+            let third = scope.spawn(|| gate.acquire(&shutdown, &tick));
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!third.is_finished());
+            assert!(tick.load(Ordering::Relaxed) > 0);
+            gate.release("a");
+            assert!(third.join().unwrap());
+        });
+        assert_eq!(*gate.state.lock(), (2, 1));
+
+        // A waiting collector gives up on shutdown
+        shutdown.store(true, Ordering::Relaxed);
+        assert!(!gate.acquire(&shutdown, &tick));
+    }
+
+    #[test]
+    fn test_unacked_budget() {
+        let budget = UnackedBudget::new(100);
+        assert_eq!(budget.try_reserve(60), 60);
+        assert_eq!(budget.try_reserve(60), 40);
+        assert_eq!(budget.try_reserve(10), 0);
+        assert_eq!(budget.used(), 100);
+        budget.release(30);
+        assert_eq!(budget.try_reserve(60), 30);
+        // Over-release never wraps
+        budget.release(500);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn test_idle_delay() {
+        let base = Duration::from_secs(5);
+        let max = Duration::from_secs(60);
+        assert_eq!(idle_delay(base, max, 100, None), base);
+        assert_eq!(idle_delay(base, max, 2, Some(3)), base);
+        assert_eq!(idle_delay(base, max, 3, Some(3)), Duration::from_secs(10));
+        assert_eq!(idle_delay(base, max, 4, Some(3)), Duration::from_secs(20));
+        assert_eq!(idle_delay(base, max, 6, Some(3)), max);
+        assert_eq!(idle_delay(base, max, u32::MAX, Some(1)), max);
+        // A cap below the poll interval never shortens it
+        assert_eq!(idle_delay(base, Duration::from_secs(1), 9, Some(1)), base);
     }
 
     #[test]
     fn test_backoff_delay_min() {
         let base = Duration::from_millis(100);
-        assert_eq!(backoff_delay(base, 1), Duration::from_millis(200));
+        let mut backoff = Backoff::new(base, MAX_BACKOFF, RetryJitter::None);
+        assert_eq!(
+            backoff_delay(&mut backoff, base, 1),
+            Duration::from_millis(200)
+        );
+    }
+
+    /// Collector for source `a` at `gatewayd`, with its config and cursor in
+    /// `dir`. `settings` and `source_settings` are extra TOML lines.
+    fn test_collector(
+        dir: &std::path::Path,
+        settings: &str,
+        source_settings: &str,
+        gatewayd: &str,
+        otlp: &str,
+    ) -> Collector {
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            format!(
+                "otlp_endpoint = \"{}\"\ncursor_dir = \"{}\"\n{}\n\n\
+                 [[sources]]\nname = \"a\"\nurl = \"{}\"\n{}\n",
+                otlp,
+                dir.display(),
+                settings,
+                gatewayd,
+                source_settings
+            ),
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        let otlp = Arc::new(crate::build_otlp_client(&config, None).unwrap());
+        let source = config.sources[0].clone();
+        let cursor = CursorManager::new(dir, &source.name).unwrap();
+        Collector::new(source, &config, otlp, cursor, None).unwrap()
+    }
+
+    #[test]
+    fn test_filter_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let unreachable = "http://127.0.0.1:1";
+        let now_us = current_unix_ms() * 1000;
+        let minutes_ago = |m: u64| now_us - m * 60_000_000;
+        let entry = |cursor: &str, realtime: u64, source_realtime: Option<u64>| JournalEntry {
+            cursor: cursor.to_string(),
+            realtime_timestamp: realtime,
+            extra_fields: source_realtime
+                .map(|t| HashMap::from([("_SOURCE_REALTIME_TIMESTAMP".to_string(), t.to_string())]))
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        let entries = || {
+            vec![
+                entry("fresh", minutes_ago(59), None),
+                entry("stale", minutes_ago(61), None),
+                // Logged long ago, received by journald recently
+                entry("late", minutes_ago(1), Some(minutes_ago(120))),
+            ]
+        };
+        let cursors = |entries: Vec<JournalEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.cursor).collect()
+        };
+
+        let collector = test_collector(dir.path(), "", "", unreachable, unreachable);
+        assert_eq!(cursors(collector.filter_stale(entries())).len(), 3);
+
+        let collector = test_collector(
+            dir.path(),
+            "",
+            "max_entry_age = \"1h\"",
+            unreachable,
+            unreachable,
+        );
+        assert_eq!(
+            cursors(collector.filter_stale(entries())),
+            ["fresh", "late"]
+        );
+
+        // The age follows timestamp_source
+        let collector = test_collector(
+            dir.path(),
+            "timestamp_source = \"source\"",
+            "max_entry_age = \"1h\"",
+            unreachable,
+            unreachable,
+        );
+        assert_eq!(cursors(collector.filter_stale(entries())), ["fresh"]);
+    }
+
+    #[test]
+    fn test_held_entries_survive_cursor_reset() {
+        let entry = r#"{"__CURSOR":"c1","__REALTIME_TIMESTAMP":"1","MESSAGE":"x"}"#;
+        let (gatewayd, _) = serve(vec![
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}\n",
+                entry.len() + 1,
+                entry
+            ),
+            "HTTP/1.1 410 Gone\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n".to_string(),
+        ]);
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+        let (otlp, exported) = serve(vec![ok.to_string()]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut collector = test_collector(
+            dir.path(),
+            "max_batch_age = \"200ms\"",
+            "",
+            &gatewayd,
+            &otlp,
+        );
+
+        // Held for batch assembly
+        assert_eq!(collector.poll().unwrap(), 1);
+        assert_eq!(collector.pending.entries.len(), 1);
+        assert_eq!(exported.lock().len(), 0);
+
+        // The next fetch hits a 410 and finds nothing new; the held entry
+        // is still forwarded once the batch is due
+        std::thread::sleep(Duration::from_millis(250));
+        assert_eq!(collector.poll().unwrap(), 0);
+        assert!(collector.pending.entries.is_empty());
+        assert_eq!(exported.lock().len(), 1);
+    }
+
+    #[test]
+    fn test_resume_skip_does_not_outlive_cursor_reset() {
+        let entry = r#"{"__CURSOR":"c1","__REALTIME_TIMESTAMP":"1","MESSAGE":"x"}"#;
+        let (gatewayd, requests) = serve(vec![
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}\n",
+                entry.len() + 1,
+                entry
+            ),
+            "HTTP/1.1 410 Gone\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n".to_string(),
+        ]);
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+        let (otlp, _) = serve(vec![ok.to_string()]);
+
+        let dir = tempfile::tempdir().unwrap();
+        // A cursor from 1970 is far older than max_resume_age
+        CursorManager::new(dir.path(), "a")
+            .unwrap()
+            .save("s=abc;i=1;t=1")
+            .unwrap();
+        let mut collector =
+            test_collector(dir.path(), "max_resume_age = \"1h\"", "", &gatewayd, &otlp);
+
+        assert_eq!(collector.poll().unwrap(), 1);
+        assert_eq!(collector.poll().unwrap(), 0);
+        let requests = requests.lock();
+        assert_eq!(requests.len(), 3);
+        assert!(
+            requests[0].head.contains("range: realtime="),
+            "{}",
+            requests[0].head
+        );
+        assert!(
+            requests[1].head.contains("range: entries=c1"),
+            "{}",
+            requests[1].head
+        );
+        // After the 410 the fetch starts from the current boot again
+        assert!(requests[2].head.contains("?boot"), "{}", requests[2].head);
+        assert!(
+            !requests[2].head.contains("realtime="),
+            "{}",
+            requests[2].head
+        );
+    }
+
+    #[test]
+    fn test_dropped_follow_stream_reconnects() {
+        let entry = r#"{"__CURSOR":"c1","__REALTIME_TIMESTAMP":"1","MESSAGE":"x"}"#;
+        // Closed mid-stream with no framing, then closed cleanly
+        let (gatewayd, requests) = serve(vec![
+            format!("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}\n", entry),
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        ]);
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+        let (otlp, _) = serve(vec![ok.to_string()]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut collector = test_collector(dir.path(), "", "mode = \"follow\"", &gatewayd, &otlp);
+        let metrics = Arc::new(MetricsState::new());
+        collector.metrics = Some(metrics.clone());
+
+        // Entries read before the drop are forwarded and checkpointed
+        assert_eq!(collector.poll().unwrap(), 1);
+        assert!(collector.stream_dropped);
+        assert_eq!(collector.cursor.load().as_deref(), Some("c1"));
+        assert!(
+            metrics
+                .render()
+                .contains("ojgf_stream_reconnects_total{source=\"a\"} 1")
+        );
+
+        // The reconnect resumes from the saved cursor
+        assert_eq!(collector.poll().unwrap(), 0);
+        assert!(!collector.stream_dropped);
+        let requests = requests.lock();
+        assert!(
+            requests[1].head.contains("range: entries=c1"),
+            "{}",
+            requests[1].head
+        );
+    }
+
+    #[test]
+    fn test_reconnect_delay() {
+        let mut backoff = Backoff::new(
+            STREAM_RECONNECT_BACKOFF,
+            MAX_STREAM_RECONNECT_DELAY,
+            RetryJitter::None,
+        );
+        assert_eq!(reconnect_delay(&mut backoff, 1), Duration::ZERO);
+        assert_eq!(reconnect_delay(&mut backoff, 2), STREAM_RECONNECT_BACKOFF);
+        assert_eq!(
+            reconnect_delay(&mut backoff, 3),
+            STREAM_RECONNECT_BACKOFF * 2
+        );
+        assert_eq!(
+            reconnect_delay(&mut backoff, 100),
+            MAX_STREAM_RECONNECT_DELAY
+        );
+    }
+
+    #[test]
+    fn test_resume_skip_survives_restart_before_first_forward() {
+        let no_content = "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n".to_string();
+        let (gatewayd, requests) = serve(vec![no_content.clone(), no_content]);
+        let dir = tempfile::tempdir().unwrap();
+        // A cursor from 1970 is far older than max_resume_age
+        CursorManager::new(dir.path(), "a")
+            .unwrap()
+            .save("s=abc;i=1;t=1")
+            .unwrap();
+
+        for _ in 0..2 {
+            let mut collector = test_collector(
+                dir.path(),
+                "max_resume_age = \"1h\"",
+                "",
+                &gatewayd,
+                "http://127.0.0.1:1",
+            );
+            assert_eq!(collector.poll().unwrap(), 0);
+            // Nothing forwarded yet, so the old cursor is kept on disk
+            assert_eq!(collector.cursor.load().as_deref(), Some("s=abc;i=1;t=1"));
+        }
+
+        // The restarted collector skips the backlog again
+        let requests = requests.lock();
+        assert_eq!(requests.len(), 2);
+        for request in requests.iter() {
+            assert!(
+                request.head.contains("range: realtime="),
+                "{}",
+                request.head
+            );
+            assert!(!request.head.contains("s=abc"), "{}", request.head);
+        }
     }
 }
@@ -1,13 +1,18 @@
 //! Cursor persistence for crash-safe resume.
 //!
 //! Cursors track the last successfully forwarded entry per source.
-//! - Stored as plain text files: `{cursor_dir}/{source_name}.cursor`
-//! - Updated atomically (write to `.tmp`, rename)
+//! - Stored as plain text files: `{cursor_dir}/{source_name}.cursor`, or
+//!   with `cursor_backend = "sqlite"` as rows in `{cursor_dir}/cursors.db`
+//! - Updated atomically (write to `.tmp` and rename, or one transaction)
 //! - Only advanced after successful OTLP push
 
+use parking_lot::Mutex;
+use rusqlite::{Connection, OptionalExtension, params};
 use std::fs;
-use std::io;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tracing::{debug, warn};
 
@@ -19,12 +24,271 @@ pub enum CursorError {
     Write(io::Error),
     #[error("Failed to rename cursor file: {0}")]
     Rename(io::Error),
+    #[error("Cursor directory {} is not writable: {source}", path.display())]
+    NotWritable { path: PathBuf, source: io::Error },
+    #[error("Cursor self-test failed in {}: {message}", path.display())]
+    SelfTest { path: PathBuf, message: String },
+    #[error(
+        "Cursor directory {} is locked by another forwarder{}; pass --allow-shared-cursor-dir to start anyway",
+        path.display(),
+        holder.as_ref().map(|pid| format!(" (pid {})", pid)).unwrap_or_default()
+    )]
+    Locked {
+        path: PathBuf,
+        holder: Option<String>,
+    },
+    #[error("Failed to lock cursor directory: {0}")]
+    Lock(io::Error),
+    #[error("Cursor database error: {0}")]
+    Database(rusqlite::Error),
+}
+
+/// Realtime timestamp of the entry a journal cursor points at, from its
+/// `t=` field (hex microseconds since the epoch).
+pub fn cursor_realtime(cursor: &str) -> Option<SystemTime> {
+    let hex = cursor.split(';').find_map(|part| part.strip_prefix("t="))?;
+    let micros = u64::from_str_radix(hex, 16).ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_micros(micros))
+}
+
+/// Name of the lock file taken in the cursor directory.
+const LOCK_FILE: &str = ".lock";
+
+/// Advisory lock on a cursor directory, released when dropped (or when the
+/// process exits).
+#[derive(Debug)]
+pub struct DirLock {
+    _file: fs::File,
+}
+
+/// Take an exclusive advisory lock (`flock`) on `cursor_dir/.lock` so a
+/// second forwarder pointed at the same directory refuses to start instead
+/// of overwriting this one's cursors. The holder's PID is written to the
+/// file for the error message.
+pub fn lock_dir(cursor_dir: &Path) -> Result<DirLock, CursorError> {
+    let path = cursor_dir.join(LOCK_FILE);
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(CursorError::Lock)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => {
+            let holder = fs::read_to_string(&path)
+                .ok()
+                .map(|pid| pid.trim().to_string())
+                .filter(|pid| !pid.is_empty());
+            return Err(CursorError::Locked {
+                path: cursor_dir.to_path_buf(),
+                holder,
+            });
+        }
+        Err(fs::TryLockError::Error(e)) => return Err(CursorError::Lock(e)),
+    }
+    file.set_len(0).map_err(CursorError::Lock)?;
+    writeln!(file, "{}", std::process::id()).map_err(CursorError::Lock)?;
+    Ok(DirLock { _file: file })
+}
+
+/// Fail fast if cursor files cannot be written to `cursor_dir`, by creating
+/// (if needed) the directory and writing then removing a probe file.
+///
+/// Without this, a read-only directory only surfaces after forwarding, and
+/// every poll re-forwards the same entries.
+pub fn check_writable(cursor_dir: &Path) -> Result<(), CursorError> {
+    let not_writable = |source| CursorError::NotWritable {
+        path: cursor_dir.to_path_buf(),
+        source,
+    };
+    fs::create_dir_all(cursor_dir).map_err(not_writable)?;
+    let probe = cursor_dir.join(".write-probe");
+    fs::write(&probe, b"").map_err(not_writable)?;
+    fs::remove_file(&probe).map_err(not_writable)
+}
+
+/// Exercise the cursor save path on `cursor_dir`'s filesystem: write and
+/// fsync a probe, rename it over a target, fsync the directory, and read it
+/// back. Catches mounts where the write-then-rename used by
+/// [`CursorManager::save`] does not hold up.
+pub fn self_test(cursor_dir: &Path) -> Result<(), CursorError> {
+    let fail = |message: String| CursorError::SelfTest {
+        path: cursor_dir.to_path_buf(),
+        message,
+    };
+    let tmp = cursor_dir.join(".self-test.cursor.tmp");
+    let target = cursor_dir.join(".self-test.cursor");
+    let probe = "s=self-test;i=1";
+
+    let result = (|| {
+        fs::write(&target, "stale").map_err(|e| fail(format!("write: {}", e)))?;
+        let mut file = fs::File::create(&tmp).map_err(|e| fail(format!("create: {}", e)))?;
+        file.write_all(probe.as_bytes())
+            .and_then(|_| file.sync_all())
+            .map_err(|e| fail(format!("write/fsync: {}", e)))?;
+        drop(file);
+
+        fs::rename(&tmp, &target).map_err(|e| fail(format!("rename over target: {}", e)))?;
+        fs::File::open(cursor_dir)
+            .and_then(|dir| dir.sync_all())
+            .map_err(|e| fail(format!("directory fsync: {}", e)))?;
+
+        if tmp.exists() {
+            return Err(fail(
+                "temporary file still present after rename".to_string(),
+            ));
+        }
+        let read = fs::read_to_string(&target).map_err(|e| fail(format!("re-read: {}", e)))?;
+        if read != probe {
+            return Err(fail(format!(
+                "re-read returned {:?}, expected {:?}",
+                read, probe
+            )));
+        }
+        Ok(())
+    })();
+
+    let _ = fs::remove_file(&tmp);
+    let _ = fs::remove_file(&target);
+    result
+}
+
+/// Filesystems whose contents do not survive a reboot.
+const VOLATILE_FILESYSTEMS: &[&str] = &["tmpfs", "ramfs"];
+
+/// Name the filesystem type if `cursor_dir` lives on a volatile mount
+/// (per `/proc/self/mounts`), where cursors are lost on reboot.
+pub fn volatile_filesystem(cursor_dir: &Path) -> Option<String> {
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    let dir = cursor_dir.canonicalize().ok()?;
+    mount_fs_type(&mounts, &dir).filter(|fs_type| VOLATILE_FILESYSTEMS.contains(&fs_type.as_str()))
+}
+
+/// Filesystem type of the longest mount point containing `path`, from
+/// `/proc/self/mounts`-formatted text.
+fn mount_fs_type(mounts: &str, path: &Path) -> Option<String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let mount_point = parts.nth(1)?.replace("\\040", " ");
+            let fs_type = parts.next()?;
+            Some((PathBuf::from(mount_point), fs_type))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.as_os_str().len())
+        .map(|(_, fs_type)| fs_type.to_string())
+}
+
+/// Run [`self_test`] and [`volatile_filesystem`] and describe any problem
+/// found, for reporting as warnings.
+pub fn storage_warnings(cursor_dir: &Path) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Err(e) = self_test(cursor_dir) {
+        warnings.push(format!("{}; cursors may not persist reliably", e));
+    }
+    if let Some(fs_type) = volatile_filesystem(cursor_dir) {
+        warnings.push(format!(
+            "cursor_dir {} is on {}; cursors will be lost on reboot",
+            cursor_dir.display(),
+            fs_type
+        ));
+    }
+    warnings
+}
+
+/// Sanitize a source name for filesystem safety. Distinct source names that
+/// sanitize to the same value would share a cursor file.
+pub fn sanitize_name(source_name: &str) -> String {
+    source_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// File name of the SQLite cursor database within `cursor_dir`.
+pub const SQLITE_FILE: &str = "cursors.db";
+
+/// SQLite database holding every source's cursor, shared by their
+/// [`CursorManager`]s.
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open (creating if needed) `{cursor_dir}/cursors.db`.
+    pub fn open(cursor_dir: &Path) -> Result<Arc<Self>, CursorError> {
+        if !cursor_dir.exists() {
+            fs::create_dir_all(cursor_dir).map_err(CursorError::CreateDir)?;
+        }
+        let conn = Connection::open(cursor_dir.join(SQLITE_FILE)).map_err(CursorError::Database)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS cursors (
+                 source TEXT PRIMARY KEY,
+                 cursor TEXT NOT NULL,
+                 saved_at_ms INTEGER NOT NULL
+             );",
+        )
+        .map_err(CursorError::Database)?;
+        Ok(Arc::new(Self {
+            conn: Mutex::new(conn),
+        }))
+    }
+
+    fn load(&self, source_name: &str) -> rusqlite::Result<Option<(String, i64)>> {
+        self.conn
+            .lock()
+            .query_row(
+                "SELECT cursor, saved_at_ms FROM cursors WHERE source = ?1",
+                params![source_name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+    }
+
+    fn save(&self, source_name: &str, cursor: &str) -> rusqlite::Result<()> {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO cursors (source, cursor, saved_at_ms) VALUES (?1, ?2, ?3)
+             ON CONFLICT (source) DO UPDATE SET cursor = ?2, saved_at_ms = ?3",
+            params![source_name, cursor, now_ms],
+        )?;
+        tx.commit()
+    }
+
+    fn reset(&self, source_name: &str) -> rusqlite::Result<usize> {
+        self.conn.lock().execute(
+            "DELETE FROM cursors WHERE source = ?1",
+            params![source_name],
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    File(PathBuf),
+    Sqlite(Arc<SqliteStore>),
 }
 
 /// Cursor manager for a single source
 #[derive(Debug, Clone)]
 pub struct CursorManager {
-    cursor_path: PathBuf,
+    backend: Backend,
     source_name: String,
 }
 
@@ -36,45 +300,48 @@ impl CursorManager {
             fs::create_dir_all(cursor_dir).map_err(CursorError::CreateDir)?;
         }
 
-        // Sanitize source name for filesystem safety
-        let safe_name = source_name
-            .chars()
-            .map(|c| {
-                if c.is_alphanumeric() || c == '-' || c == '_' {
-                    c
-                } else {
-                    '_'
-                }
-            })
-            .collect::<String>();
-
-        let cursor_path = cursor_dir.join(format!("{}.cursor", safe_name));
+        let cursor_path = cursor_dir.join(format!("{}.cursor", sanitize_name(source_name)));
 
         Ok(Self {
-            cursor_path,
+            backend: Backend::File(cursor_path),
             source_name: source_name.to_string(),
         })
     }
 
+    /// Create a cursor manager storing the source's cursor in `store`,
+    /// keyed by its unsanitized name.
+    pub fn sqlite(store: Arc<SqliteStore>, source_name: &str) -> Self {
+        Self {
+            backend: Backend::Sqlite(store),
+            source_name: source_name.to_string(),
+        }
+    }
+
     /// Load the current cursor, if it exists
     pub fn load(&self) -> Option<String> {
-        match fs::read_to_string(&self.cursor_path) {
-            Ok(cursor) => {
-                let cursor = cursor.trim().to_string();
-                if cursor.is_empty() {
-                    debug!(source = %self.source_name, "Cursor file is empty");
-                    None
-                } else {
-                    debug!(source = %self.source_name, cursor = %cursor, "Loaded cursor");
-                    Some(cursor)
-                }
+        let loaded = match &self.backend {
+            Backend::File(cursor_path) => match fs::read_to_string(cursor_path) {
+                // An empty file counts as no cursor
+                Ok(cursor) => Ok(Some(cursor.trim().to_string()).filter(|c| !c.is_empty())),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.to_string()),
+            },
+            Backend::Sqlite(store) => store
+                .load(&self.source_name)
+                .map(|row| row.map(|(cursor, _)| cursor))
+                .map_err(|e| e.to_string()),
+        };
+        match loaded {
+            Ok(Some(cursor)) => {
+                debug!(source = %self.source_name, cursor = %cursor, "Loaded cursor");
+                Some(cursor)
             }
-            Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                debug!(source = %self.source_name, "No cursor file found, starting fresh");
+            Ok(None) => {
+                debug!(source = %self.source_name, "No saved cursor, starting fresh");
                 None
             }
             Err(e) => {
-                warn!(source = %self.source_name, error = %e, "Failed to read cursor file, starting fresh");
+                warn!(source = %self.source_name, error = %e, "Failed to read cursor, starting fresh");
                 None
             }
         }
@@ -82,22 +349,54 @@ impl CursorManager {
 
     /// Save the cursor atomically
     pub fn save(&self, cursor: &str) -> Result<(), CursorError> {
-        let tmp_path = self.cursor_path.with_extension("cursor.tmp");
+        match &self.backend {
+            Backend::File(cursor_path) => {
+                let tmp_path = cursor_path.with_extension("cursor.tmp");
 
-        // Write to temp file
-        fs::write(&tmp_path, cursor).map_err(CursorError::Write)?;
+                // Write to temp file
+                fs::write(&tmp_path, cursor).map_err(CursorError::Write)?;
 
-        // Atomic rename
-        fs::rename(&tmp_path, &self.cursor_path).map_err(CursorError::Rename)?;
+                // Atomic rename
+                fs::rename(&tmp_path, cursor_path).map_err(CursorError::Rename)?;
+            }
+            Backend::Sqlite(store) => store
+                .save(&self.source_name, cursor)
+                .map_err(CursorError::Database)?,
+        }
 
         debug!(source = %self.source_name, cursor = %cursor, "Saved cursor");
         Ok(())
     }
 
-    /// Reset the cursor (delete file)
+    /// When the cursor was last saved, if it exists.
+    pub fn saved_at(&self) -> Option<SystemTime> {
+        match &self.backend {
+            Backend::File(cursor_path) => fs::metadata(cursor_path).and_then(|m| m.modified()).ok(),
+            Backend::Sqlite(store) => {
+                let (_, saved_at_ms) = store.load(&self.source_name).ok()??;
+                UNIX_EPOCH.checked_add(Duration::from_millis(saved_at_ms.try_into().ok()?))
+            }
+        }
+    }
+
+    /// Reset the cursor (delete its file or row)
     pub fn reset(&self) -> Result<(), CursorError> {
-        if self.cursor_path.exists() {
-            fs::remove_file(&self.cursor_path).map_err(CursorError::Write)?;
+        let removed = match &self.backend {
+            Backend::File(cursor_path) => {
+                let exists = cursor_path.exists();
+                if exists {
+                    fs::remove_file(cursor_path).map_err(CursorError::Write)?;
+                }
+                exists
+            }
+            Backend::Sqlite(store) => {
+                store
+                    .reset(&self.source_name)
+                    .map_err(CursorError::Database)?
+                    > 0
+            }
+        };
+        if removed {
             debug!(source = %self.source_name, "Reset cursor");
         }
         Ok(())
@@ -128,14 +427,112 @@ mod tests {
         assert!(cm.load().is_none());
     }
 
+    #[test]
+    fn test_check_writable() {
+        let dir = TempDir::new().unwrap();
+        let nested = dir.path().join("state/cursors");
+        check_writable(&nested).unwrap();
+        assert!(nested.is_dir());
+        assert_eq!(fs::read_dir(&nested).unwrap().count(), 0);
+
+        // A file where the directory should be can never hold cursors
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        let err = check_writable(&file.join("cursors")).unwrap_err();
+        assert!(matches!(err, CursorError::NotWritable { .. }));
+        assert!(err.to_string().contains("is not writable"));
+    }
+
+    #[test]
+    fn test_lock_dir_excludes_second_holder() {
+        let dir = TempDir::new().unwrap();
+        let lock = lock_dir(dir.path()).unwrap();
+
+        let err = lock_dir(dir.path()).unwrap_err();
+        assert!(matches!(
+            &err,
+            CursorError::Locked { holder: Some(pid), .. } if *pid == std::process::id().to_string()
+        ));
+
+        drop(lock);
+        lock_dir(dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_cursor_realtime() {
+        let cursor = "s=739ad463348b4ceca5a9e69c95a3c93f;i=4ece7;b=6c7c6013a8394e3b8a4f1b2c3d4e5f60;m=5a1b2c3d;t=5d6e7f8091a2b;x=f49c3a2b1d0e5c6f";
+        assert_eq!(
+            cursor_realtime(cursor),
+            Some(UNIX_EPOCH + Duration::from_micros(0x5d6e7f8091a2b))
+        );
+        assert_eq!(cursor_realtime("s=abc;i=1"), None);
+        assert_eq!(cursor_realtime("s=abc;t=zz"), None);
+    }
+
+    #[test]
+    fn test_self_test() {
+        let dir = TempDir::new().unwrap();
+        self_test(dir.path()).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let err = self_test(&dir.path().join("missing")).unwrap_err();
+        assert!(matches!(err, CursorError::SelfTest { .. }));
+    }
+
+    #[test]
+    fn test_mount_fs_type() {
+        let mounts = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+tmpfs /run tmpfs rw,nosuid 0 0
+/dev/sdb1 /var/lib xfs rw 0 0
+tmpfs /var/lib/my\\040state tmpfs rw 0 0
+";
+        let fs_type = |path: &str| mount_fs_type(mounts, Path::new(path));
+        assert_eq!(fs_type("/var/lib/ojgf").as_deref(), Some("xfs"));
+        assert_eq!(fs_type("/run/ojgf").as_deref(), Some("tmpfs"));
+        assert_eq!(fs_type("/var/lib/my state/x").as_deref(), Some("tmpfs"));
+        assert_eq!(fs_type("/running").as_deref(), Some("ext4"));
+    }
+
     #[test]
     fn test_cursor_sanitizes_name() {
         let dir = TempDir::new().unwrap();
         let cm = CursorManager::new(dir.path(), "host/with:special<chars>").unwrap();
+        let Backend::File(cursor_path) = &cm.backend else {
+            panic!("expected a file backend");
+        };
         assert!(
-            cm.cursor_path
+            cursor_path
                 .to_string_lossy()
                 .contains("host_with_special_chars_")
         );
     }
+
+    #[test]
+    fn test_sqlite_cursor_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteStore::open(&dir.path().join("state")).unwrap();
+        let a = CursorManager::sqlite(store.clone(), "host/a");
+        let b = CursorManager::sqlite(store, "host:a");
+
+        assert!(a.load().is_none());
+        assert!(a.saved_at().is_none());
+        a.save("s=abc;i=1").unwrap();
+        a.save("s=abc;i=2").unwrap();
+        b.save("s=def;i=9").unwrap();
+        assert_eq!(a.load(), Some("s=abc;i=2".to_string()));
+        assert_eq!(b.load(), Some("s=def;i=9".to_string()));
+        assert!(a.saved_at().is_some());
+
+        a.reset().unwrap();
+        assert!(a.load().is_none());
+        assert_eq!(b.load(), Some("s=def;i=9".to_string()));
+
+        // Cursors survive reopening the database
+        let reopened = SqliteStore::open(&dir.path().join("state")).unwrap();
+        assert_eq!(
+            CursorManager::sqlite(reopened, "host:a").load(),
+            Some("s=def;i=9".to_string())
+        );
+    }
 }
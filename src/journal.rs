@@ -3,18 +3,35 @@
 //! Fetches journal entries from systemd-journal-gatewayd endpoints.
 //! See: https://www.freedesktop.org/software/systemd/man/latest/systemd-journal-gatewayd.service.html
 
-use crate::config::TlsConfig;
-use reqwest::StatusCode;
+use crate::backoff::Backoff;
+use crate::config::{
+    FieldFilter, FieldNormalization, HmacConfig, JournalFormat, RetryJitter, TlsConfig,
+};
+use crate::policy::SourcePolicy;
+use parking_lot::{Condvar, Mutex};
 use reqwest::blocking::Client;
+use reqwest::header::TRANSFER_ENCODING;
+use reqwest::{StatusCode, Version};
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::time::Duration;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tracing::{debug, trace, warn};
 
 /// HTTP timeout for gatewayd requests
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Wait for the headers and for each read on a follow stream; bounds how
+/// long shutdown waits. The stream itself has no overall deadline.
+const FOLLOW_READ_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long a follow request may stay open before returning what it has.
+const FOLLOW_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// How long an idle follow request is held open before reconnecting.
+const FOLLOW_MAX_WAIT: Duration = Duration::from_secs(30);
+
 #[derive(Error, Debug)]
 pub enum JournalError {
     #[error("HTTP request failed: {0}")]
@@ -25,10 +42,48 @@ pub enum JournalError {
     InvalidCursor,
     #[error("Server error: {status}")]
     ServerError { status: StatusCode },
+    #[error("Unauthorized ({status}); check the source's credentials")]
+    Unauthorized { status: StatusCode },
     #[error("Configuration error: {0}")]
     Config(String),
+    #[error("Malformed journal export response: {0}")]
+    Export(String),
+}
+
+impl JournalError {
+    /// Whether a retry might succeed: connection failures, timeouts and 5xx.
+    fn is_transient(&self) -> bool {
+        match self {
+            JournalError::Http(e) => e.is_connect() || e.is_timeout(),
+            JournalError::ServerError { status } => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+/// Error for a gatewayd status other than 200 or 204.
+fn status_error(status: StatusCode) -> JournalError {
+    match status {
+        StatusCode::GONE => {
+            warn!("Cursor is no longer valid (410 Gone)");
+            JournalError::InvalidCursor
+        }
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => JournalError::Unauthorized { status },
+        _ => JournalError::ServerError { status },
+    }
+}
+
+/// Whether a response body read failed only because no data arrived in time.
+fn is_read_timeout(e: &std::io::Error) -> bool {
+    e.kind() == std::io::ErrorKind::TimedOut
+        || e.get_ref()
+            .and_then(|inner| inner.downcast_ref::<reqwest::Error>())
+            .is_some_and(reqwest::Error::is_timeout)
 }
 
+/// Upper bound on a single fetch retry delay.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
 /// A journal entry from gatewayd
 #[derive(Debug, Clone, Default)]
 #[allow(dead_code)]
@@ -43,7 +98,11 @@ pub struct JournalEntry {
     pub boot_id: Option<String>,
     /// Log message
     pub message: String,
-    /// Priority (0-7)
+    /// Original `MESSAGE` bytes when they are not valid UTF-8 (`message`
+    /// then holds a lossy conversion)
+    pub message_bytes: Option<Vec<u8>>,
+    /// Priority, normally 0-7; an unparseable `PRIORITY` is left `None` and
+    /// kept in `extra_fields`
     pub priority: Option<u8>,
     /// Systemd unit name
     pub systemd_unit: Option<String>,
@@ -65,6 +124,8 @@ pub struct JournalEntry {
     pub hostname: Option<String>,
     /// All other fields
     pub extra_fields: HashMap<String, String>,
+    /// Original JSON line as returned by gatewayd, when requested
+    pub raw: Option<String>,
 }
 
 /// Raw journal entry as returned by gatewayd
@@ -104,21 +165,98 @@ struct RawJournalEntry {
     extra: HashMap<String, serde_json::Value>,
 }
 
+/// Whether `name` is kept in `extra_fields` rather than a dedicated member
+/// of [`JournalEntry`].
+pub fn is_extra_field(name: &str) -> bool {
+    !matches!(
+        name,
+        "MESSAGE"
+            | "PRIORITY"
+            | "_PID"
+            | "_UID"
+            | "_GID"
+            | "_COMM"
+            | "_EXE"
+            | "SYSLOG_IDENTIFIER"
+            | "_BOOT_ID"
+            | "_SYSTEMD_UNIT"
+            | "_MACHINE_ID"
+            | "_HOSTNAME"
+    )
+}
+
 impl JournalEntry {
+    /// Look up a field by its original journal name (e.g. `_PID`, `TRACE_ID`).
+    pub fn field(&self, name: &str) -> Option<&str> {
+        match name {
+            "MESSAGE" => Some(self.message.as_str()),
+            "_PID" => self.pid.as_deref(),
+            "_UID" => self.uid.as_deref(),
+            "_GID" => self.gid.as_deref(),
+            "_COMM" => self.comm.as_deref(),
+            "_EXE" => self.exe.as_deref(),
+            "SYSLOG_IDENTIFIER" => self.syslog_identifier.as_deref(),
+            "_BOOT_ID" => self.boot_id.as_deref(),
+            "_SYSTEMD_UNIT" => self.systemd_unit.as_deref(),
+            "_MACHINE_ID" => self.machine_id.as_deref(),
+            "_HOSTNAME" => self.hostname.as_deref(),
+            other => self.extra_fields.get(other).map(String::as_str),
+        }
+    }
+
+    /// Whether `PRIORITY` is present but not a syslog level 0-7.
+    pub fn priority_out_of_range(&self) -> bool {
+        self.priority.is_some_and(|p| p > 7) || self.extra_fields.contains_key("PRIORITY")
+    }
+
+    /// `_SOURCE_REALTIME_TIMESTAMP` in microseconds, when the entry has a valid one.
+    pub fn source_realtime_timestamp(&self) -> Option<u64> {
+        self.extra_fields
+            .get("_SOURCE_REALTIME_TIMESTAMP")
+            .and_then(|s| s.parse().ok())
+            .filter(|&us| us > 0)
+    }
+
+    /// Rename extra fields to their canonical names. When several map to one
+    /// name, the field already spelled that way wins, then the first by name.
+    fn normalize_field_names(&mut self, rules: &FieldNormalization) {
+        let mut fields: Vec<(String, String, String)> = std::mem::take(&mut self.extra_fields)
+            .into_iter()
+            .map(|(key, value)| (rules.normalize(&key), key, value))
+            .collect();
+        fields.sort_unstable_by(|a, b| (a.0 != a.1, &a.1).cmp(&(b.0 != b.1, &b.1)));
+        for (name, _, value) in fields {
+            self.extra_fields.entry(name).or_insert(value);
+        }
+    }
+
+    /// Drop the extra fields `filter` does not keep. An unparseable
+    /// `PRIORITY` stays so it is still reported.
+    fn filter_fields(&mut self, filter: &FieldFilter) {
+        self.extra_fields
+            .retain(|key, _| key == "PRIORITY" || filter.keeps(key));
+    }
+
     /// Convert a raw gatewayd entry into a structured entry, truncating
     /// `extra_fields` values to `max_field_bytes`.
     fn from_raw(raw: RawJournalEntry, max_field_bytes: usize) -> Self {
         // Parse message - can be a string or an array of bytes
-        let message = match raw.message {
-            Some(serde_json::Value::String(s)) => s,
+        let (message, message_bytes) = match raw.message {
+            Some(serde_json::Value::String(s)) => (s, None),
             Some(serde_json::Value::Array(arr)) => {
                 let bytes: Vec<u8> = arr
                     .iter()
                     .filter_map(|v| v.as_u64().map(|n| n as u8))
                     .collect();
-                String::from_utf8_lossy(&bytes).to_string()
+                match String::from_utf8(bytes) {
+                    Ok(s) => (s, None),
+                    Err(e) => (
+                        String::from_utf8_lossy(e.as_bytes()).to_string(),
+                        Some(e.into_bytes()),
+                    ),
+                }
             }
-            _ => String::new(),
+            _ => (String::new(), None),
         };
 
         fn truncate_value(s: String, max: usize) -> String {
@@ -134,7 +272,7 @@ impl JournalEntry {
         }
 
         // Convert extra fields to strings
-        let extra_fields: HashMap<String, String> = raw
+        let mut extra_fields: HashMap<String, String> = raw
             .extra
             .into_iter()
             .filter_map(|(k, v)| {
@@ -156,6 +294,14 @@ impl JournalEntry {
             })
             .collect();
 
+        // Keep an unparseable PRIORITY (e.g. "") so it is reported, not lost
+        let priority = raw.priority.as_ref().and_then(|s| s.parse().ok());
+        if priority.is_none()
+            && let Some(value) = raw.priority
+        {
+            extra_fields.insert("PRIORITY".to_string(), value);
+        }
+
         JournalEntry {
             cursor: raw.cursor,
             realtime_timestamp: raw.realtime_timestamp.parse().unwrap_or(0),
@@ -165,7 +311,8 @@ impl JournalEntry {
                 .and_then(|s| s.parse().ok()),
             boot_id: raw.boot_id,
             message,
-            priority: raw.priority.as_ref().and_then(|s| s.parse().ok()),
+            message_bytes,
+            priority,
             systemd_unit: raw.systemd_unit,
             syslog_identifier: raw.syslog_identifier,
             pid: raw.pid,
@@ -176,16 +323,217 @@ impl JournalEntry {
             machine_id: raw.machine_id,
             hostname: raw.hostname,
             extra_fields,
+            raw: None,
+        }
+    }
+}
+
+/// Per-client options controlling how entries are fetched and decoded.
+#[derive(Debug, Clone)]
+pub struct JournalOptions {
+    /// Byte cap for each extra field value (0 keeps values unbounded).
+    pub max_field_bytes: usize,
+    /// Prefer HTTP/2 (ALPN for https, prior knowledge for plain http).
+    pub http2: bool,
+    /// Keep the original JSON line on each entry (`JournalEntry::raw`).
+    pub keep_raw: bool,
+    /// Ask gatewayd to skip the cursor entry (`:1:` in the Range header).
+    /// Disable for proxies that ignore the skip; the client drops it instead.
+    pub cursor_skip: bool,
+    /// Total fetch attempts for transient failures (1 disables retries).
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles on each further attempt.
+    pub retry_backoff: Duration,
+    /// Jitter applied to retry delays.
+    pub retry_jitter: RetryJitter,
+    /// Refetches of an empty response before concluding nothing is new,
+    /// for proxies that briefly answer 204 while entries exist.
+    pub retry_empty: u32,
+    /// Delay before each refetch of an empty response.
+    pub retry_empty_delay: Duration,
+    /// Realtime position used instead of the current boot when no cursor exists.
+    pub start_timestamp: Option<SystemTime>,
+    /// Concurrent in-flight requests allowed against this gatewayd.
+    pub max_connections: usize,
+    /// Response format requested via `Accept` and parsed accordingly.
+    pub format: JournalFormat,
+    /// TLS server name (SNI and certificate check) in place of the URL host.
+    pub tls_server_name: Option<String>,
+    /// Canonicalize extra field names as they are parsed.
+    pub field_normalization: Option<FieldNormalization>,
+    /// Extra fields kept after normalization.
+    pub field_filter: FieldFilter,
+    /// Add `follow` to the query so gatewayd streams new entries.
+    pub follow: bool,
+    /// Connect only to addresses this policy permits and never follow
+    /// redirects.
+    pub source_policy: Option<SourcePolicy>,
+}
+
+impl Default for JournalOptions {
+    fn default() -> Self {
+        Self {
+            max_field_bytes: crate::config::DEFAULT_MAX_FIELD_BYTES,
+            http2: false,
+            keep_raw: false,
+            cursor_skip: true,
+            max_attempts: 1,
+            retry_backoff: crate::config::DEFAULT_FETCH_RETRY_BACKOFF,
+            retry_jitter: RetryJitter::None,
+            retry_empty: 0,
+            retry_empty_delay: crate::config::DEFAULT_RETRY_EMPTY_DELAY,
+            start_timestamp: None,
+            max_connections: 1,
+            format: JournalFormat::Json,
+            tls_server_name: None,
+            field_normalization: None,
+            field_filter: FieldFilter::default(),
+            follow: false,
+            source_policy: None,
         }
     }
 }
 
+/// Callback notified with the in-flight request count whenever it changes.
+pub type InFlightObserver = Box<dyn Fn(usize) + Send + Sync>;
+
+/// Per-request header hook, e.g. for proxies that require a time-based
+/// signature on every request.
+///
+/// Called with the full request URL and the current time before each
+/// gatewayd request; the returned headers are added to that request only.
+#[derive(Clone)]
+pub struct RequestSigner(Arc<SignFn>);
+
+type SignFn = dyn Fn(&str, SystemTime) -> Vec<(String, String)> + Send + Sync;
+
+impl RequestSigner {
+    pub fn new(
+        sign: impl Fn(&str, SystemTime) -> Vec<(String, String)> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(sign))
+    }
+
+    /// Built-in signer: puts the Unix time in seconds in `timestamp_header`
+    /// and the hex HMAC-SHA256 of `"{timestamp}\n{path_and_query}"` in `header`.
+    pub fn hmac_sha256(config: &HmacConfig) -> Self {
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, config.secret.as_bytes());
+        let header = config.header.clone();
+        let timestamp_header = config.timestamp_header.clone();
+        Self::new(move |url, now| {
+            let timestamp = now
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                .to_string();
+            let message = format!("{}\n{}", timestamp, path_and_query(url));
+            let tag = ring::hmac::sign(&key, message.as_bytes());
+            let signature: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+            vec![
+                (timestamp_header.clone(), timestamp),
+                (header.clone(), signature),
+            ]
+        })
+    }
+
+    fn headers(&self, url: &str) -> Vec<(String, String)> {
+        (self.0)(url, SystemTime::now())
+    }
+}
+
+impl std::fmt::Debug for RequestSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RequestSigner")
+    }
+}
+
+/// Path and query of `url` (`/entries?_SYSTEMD_UNIT=a.service`), or `url`
+/// itself if it does not parse.
+fn path_and_query(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        },
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Swap the host of `url` for `server_name`, returning the new URL and the
+/// addresses of the original host so connections still go there. The name
+/// then drives SNI, certificate verification and the `Host` header.
+fn with_server_name(
+    url: &str,
+    server_name: &str,
+) -> Result<(String, Vec<std::net::SocketAddr>), JournalError> {
+    use std::net::ToSocketAddrs;
+    let invalid = |e: String| JournalError::Config(format!("tls_server_name: {}", e));
+    let mut parsed = reqwest::Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| invalid("URL has no host".to_string()))?;
+    // IPv6 literals keep their brackets in host_str
+    let addrs: Vec<std::net::SocketAddr> =
+        match host.trim_matches(['[', ']']).parse::<std::net::IpAddr>() {
+            Ok(ip) => vec![(ip, port).into()],
+            Err(_) => (host, port)
+                .to_socket_addrs()
+                .map_err(|e| invalid(format!("cannot resolve '{}': {}", host, e)))?
+                .collect(),
+        };
+    parsed
+        .set_host(Some(server_name))
+        .map_err(|e| invalid(e.to_string()))?;
+    Ok((parsed.to_string(), addrs))
+}
+
+/// Entries read from one `follow` request.
+#[derive(Debug, Default)]
+pub struct FollowedEntries {
+    pub entries: Vec<JournalEntry>,
+    /// The stream broke off (reset connection, truncated body) instead of
+    /// being returned or ended by gatewayd.
+    pub dropped: bool,
+}
+
 /// Journal gatewayd client
 pub struct JournalClient {
     client: Client,
+    /// Client for `follow` streams: its timeout only limits each wait for
+    /// headers or data, so a stream can stay open up to `FOLLOW_MAX_WAIT`.
+    follow_client: Option<Client>,
+    /// HTTP/1.1 client used once an h2c prior-knowledge request has failed.
+    http1_fallback: Option<Client>,
+    http2_disabled: AtomicBool,
+    /// Set once an h2c request got a response; later failures are then
+    /// ordinary errors rather than a sign the server lacks h2c.
+    http2_confirmed: AtomicBool,
+    retries: AtomicU64,
+    in_flight: Mutex<usize>,
+    slot_free: Condvar,
+    in_flight_observer: Option<InFlightObserver>,
+    signer: Option<RequestSigner>,
+    /// Last `Server` header seen from gatewayd.
+    server_version: Mutex<Option<String>>,
+    server_version_changed: AtomicBool,
     base_url: String,
     units: Vec<String>,
-    max_field_bytes: usize,
+    options: JournalOptions,
+    /// Once raised, failed or empty fetches are no longer retried.
+    shutdown: Option<Arc<AtomicBool>>,
+}
+
+/// Permit for one in-flight request; releases the slot on drop.
+struct RequestPermit<'a>(&'a JournalClient);
+
+impl Drop for RequestPermit<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.0.in_flight.lock();
+        *in_flight -= 1;
+        self.0.observe_in_flight(*in_flight);
+        self.0.slot_free.notify_one();
+    }
 }
 
 impl JournalClient {
@@ -195,22 +543,165 @@ impl JournalClient {
         units: Vec<String>,
         tls: Option<&TlsConfig>,
         headers: &std::collections::HashMap<String, String>,
-        max_field_bytes: usize,
+        options: JournalOptions,
     ) -> Result<Self, JournalError> {
-        let client = crate::config::build_http_client(tls, headers, REQUEST_TIMEOUT)
-            .map_err(|e| JournalError::Config(e.to_string()))?;
+        let denied = |e: String| JournalError::Config(format!("source_policy: {}", e));
+        let (base_url, resolve) = match (&options.tls_server_name, &options.source_policy) {
+            (Some(name), policy) => {
+                let (url, addrs) = with_server_name(base_url, name)?;
+                if let Some(policy) = policy {
+                    policy.check_addrs(&addrs).map_err(denied)?;
+                }
+                (url, Some((name.clone(), addrs)))
+            }
+            // Resolve once and pin, so a DNS answer that changes after the
+            // check cannot point the client somewhere else.
+            (None, Some(policy)) => {
+                let (host, addrs) = policy.vetted_addrs(base_url).map_err(denied)?;
+                (base_url.to_string(), Some((host, addrs)))
+            }
+            (None, None) => (base_url.to_string(), None),
+        };
+        let redirect = || match options.source_policy {
+            // A redirect could lead to a host the policy never vetted
+            Some(_) => reqwest::redirect::Policy::none(),
+            None => reqwest::redirect::Policy::default(),
+        };
+        let builder_with = |timeout| {
+            crate::config::http_client_builder(tls, headers, timeout)
+                .map(|b| match &resolve {
+                    Some((name, addrs)) => b.resolve_to_addrs(name, addrs),
+                    None => b,
+                })
+                .map(|b| b.redirect(redirect()))
+                .map_err(|e| JournalError::Config(e.to_string()))
+        };
+        let builder = || builder_with(REQUEST_TIMEOUT);
+        let finish = |b| {
+            crate::config::finish_http_client(b).map_err(|e| JournalError::Config(e.to_string()))
+        };
+
+        let (client, http1_fallback) = if !options.http2 {
+            (finish(builder()?.http1_only())?, None)
+        } else if base_url.starts_with("http://") {
+            // Plain-text h2c needs prior knowledge; keep an HTTP/1.1 client
+            // around in case the server turns out not to speak it.
+            (
+                finish(builder()?.http2_prior_knowledge())?,
+                Some(finish(builder()?.http1_only())?),
+            )
+        } else {
+            // ALPN negotiates h2 and falls back to HTTP/1.1 on its own.
+            (finish(builder()?)?, None)
+        };
+
+        // A per-request timeout would be a deadline for the whole body, so
+        // follow streams get their own client. Long-lived streams gain
+        // nothing from h2c, so plain http stays on HTTP/1.1.
+        let follow_client = if !options.follow {
+            None
+        } else if options.http2 && !base_url.starts_with("http://") {
+            Some(finish(builder_with(FOLLOW_READ_TIMEOUT)?)?)
+        } else {
+            Some(finish(builder_with(FOLLOW_READ_TIMEOUT)?.http1_only())?)
+        };
 
         // Normalize URL (remove trailing slash)
         let base_url = base_url.trim_end_matches('/').to_string();
 
         Ok(Self {
             client,
+            follow_client,
+            http1_fallback,
+            http2_disabled: AtomicBool::new(false),
+            http2_confirmed: AtomicBool::new(false),
+            retries: AtomicU64::new(0),
+            in_flight: Mutex::new(0),
+            slot_free: Condvar::new(),
+            in_flight_observer: None,
+            signer: None,
+            server_version: Mutex::new(None),
+            server_version_changed: AtomicBool::new(false),
             base_url,
             units,
-            max_field_bytes,
+            options,
+            shutdown: None,
         })
     }
 
+    /// Change where a fetch without a cursor starts; `None` means the
+    /// current boot.
+    pub fn set_start_timestamp(&mut self, start: Option<SystemTime>) {
+        self.options.start_timestamp = start;
+    }
+
+    /// Stop retrying fetches once `shutdown` is raised, so a shutdown drain
+    /// only waits for the request already in flight.
+    pub fn set_shutdown(&mut self, shutdown: Arc<AtomicBool>) {
+        self.shutdown = Some(shutdown);
+    }
+
+    fn shutting_down(&self) -> bool {
+        self.shutdown
+            .as_ref()
+            .is_some_and(|s| s.load(Ordering::Relaxed))
+    }
+
+    /// Sleep for `delay` in 100ms slices, returning `false` early if
+    /// shutdown is raised meanwhile.
+    fn sleep_unless_shutdown(&self, delay: Duration) -> bool {
+        let mut left = delay;
+        while left > Duration::ZERO {
+            if self.shutting_down() {
+                return false;
+            }
+            let sleep = left.min(Duration::from_millis(100));
+            std::thread::sleep(sleep);
+            left = left.saturating_sub(sleep);
+        }
+        !self.shutting_down()
+    }
+
+    /// Add headers computed per request by `signer` (e.g. HMAC signatures).
+    pub fn with_request_signer(mut self, signer: RequestSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Start a GET for `url`, with any signer headers applied.
+    fn get(&self, client: &Client, url: &str) -> reqwest::blocking::RequestBuilder {
+        let mut request = client.get(url);
+        if let Some(signer) = &self.signer {
+            for (name, value) in signer.headers(url) {
+                request = request.header(name, value);
+            }
+        }
+        request
+    }
+
+    /// Report in-flight request count changes (e.g. to a metrics gauge).
+    pub fn with_in_flight_observer(mut self, observer: InFlightObserver) -> Self {
+        self.in_flight_observer = Some(observer);
+        self
+    }
+
+    /// Block until fewer than `max_connections` requests are in flight.
+    fn acquire_permit(&self) -> RequestPermit<'_> {
+        let mut in_flight = self.in_flight.lock();
+        while *in_flight >= self.options.max_connections.max(1) {
+            self.slot_free.wait(&mut in_flight);
+        }
+        *in_flight += 1;
+        self.observe_in_flight(*in_flight);
+        RequestPermit(self)
+    }
+
+    fn observe_in_flight(&self, count: usize) {
+        if let Some(observer) = &self.in_flight_observer {
+            observer(count);
+        }
+    }
+
     /// Remove the already-forwarded cursor entry that gatewayd sometimes
     /// re-serves when seeking past the journal tail.
     fn strip_seen_cursor(
@@ -231,11 +722,27 @@ impl JournalClient {
 
         // Cursor goes in the Range header; gatewayd rejects unknown URL params.
         let range = if let Some(c) = cursor {
-            format!("entries={}:1:{}", c, batch_size)
+            if self.options.cursor_skip {
+                format!("entries={}:1:{}", c, batch_size)
+            } else {
+                // The cursor entry comes back first and is stripped client-side,
+                // so ask for one extra to keep full batches full.
+                format!("entries={}:0:{}", c, batch_size + 1)
+            }
+        } else if let Some(start) = self.options.start_timestamp {
+            // gatewayd takes realtime ranges in seconds since the epoch
+            let since = start
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            format!("realtime={}::0:{}", since, batch_size)
         } else {
             query_parts.push("boot".to_string());
             format!("entries=:{}", batch_size)
         };
+        if self.options.follow {
+            query_parts.push("follow".to_string());
+        }
 
         // Add unit filters
         for unit in &self.units {
@@ -252,43 +759,307 @@ impl JournalClient {
     /// Fetch journal entries
     ///
     /// If cursor is Some, fetch entries after that cursor.
-    /// If cursor is None, fetch entries from current boot.
+    /// If cursor is None, fetch entries from `start_timestamp` when set,
+    /// otherwise from the current boot.
+    ///
+    /// Connection errors, timeouts and 5xx responses are retried up to
+    /// `max_attempts` with exponential backoff; 410 and other 4xx are not.
+    /// An empty response is refetched up to `retry_empty` times. Neither
+    /// happens after shutdown (see [`JournalClient::set_shutdown`]).
     pub fn fetch(
         &self,
         cursor: Option<&str>,
         batch_size: usize,
+    ) -> Result<Vec<JournalEntry>, JournalError> {
+        let mut entries = self.fetch_with_retries(cursor, batch_size)?;
+        for retry in 1..=self.options.retry_empty {
+            if !entries.is_empty() || self.shutting_down() {
+                break;
+            }
+            debug!(
+                url = %self.base_url,
+                retry = retry,
+                "Empty journal response, refetching"
+            );
+            if !self.sleep_unless_shutdown(self.options.retry_empty_delay) {
+                break;
+            }
+            entries = self.fetch_with_retries(cursor, batch_size)?;
+        }
+        Ok(entries)
+    }
+
+    fn fetch_with_retries(
+        &self,
+        cursor: Option<&str>,
+        batch_size: usize,
+    ) -> Result<Vec<JournalEntry>, JournalError> {
+        let mut backoff = Backoff::new(
+            self.options.retry_backoff,
+            MAX_RETRY_DELAY,
+            self.options.retry_jitter,
+        );
+        let mut attempt = 1;
+        loop {
+            match self.fetch_once(cursor, batch_size) {
+                Err(e)
+                    if attempt < self.options.max_attempts
+                        && e.is_transient()
+                        && !self.shutting_down() =>
+                {
+                    let delay = backoff.delay(attempt);
+                    debug!(
+                        url = %self.base_url,
+                        attempt = attempt,
+                        delay_ms = delay.as_millis(),
+                        error = %e,
+                        "Journal fetch failed, retrying"
+                    );
+                    self.retries.fetch_add(1, Ordering::Relaxed);
+                    if !self.sleep_unless_shutdown(delay) {
+                        return Err(e);
+                    }
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Check that gatewayd accepts connections by requesting `/machine`.
+    ///
+    /// Any HTTP response counts as reachable; only transport errors
+    /// (connection refused, DNS, timeouts) fail.
+    pub fn probe(&self) -> Result<(), JournalError> {
+        let url = format!("{}/machine", self.base_url);
+        let send = |client: &Client| {
+            self.get(client, &url)
+                .header("Accept", "application/json")
+                .send()
+        };
+        let response = match &self.http1_fallback {
+            Some(fallback) if self.http2_disabled() => send(fallback)?,
+            // An HTTP/1.1-only server still counts as reachable
+            Some(fallback) => send(&self.client).or_else(|_| send(fallback))?,
+            None => send(&self.client)?,
+        };
+        trace!(status = %response.status(), "Probe response");
+        Ok(())
+    }
+
+    /// Number of fetch retries since the last call, resetting the counter.
+    pub fn take_retries(&self) -> u64 {
+        self.retries.swap(0, Ordering::Relaxed)
+    }
+
+    /// The gatewayd `Server` header if it changed since the last call,
+    /// e.g. `systemd-journal-gatewayd/255`.
+    pub fn take_server_version_change(&self) -> Option<String> {
+        if self.server_version_changed.swap(false, Ordering::Relaxed) {
+            self.server_version.lock().clone()
+        } else {
+            None
+        }
+    }
+
+    fn observe_server_version(&self, headers: &reqwest::header::HeaderMap) {
+        let Some(version) = headers
+            .get(reqwest::header::SERVER)
+            .and_then(|v| v.to_str().ok())
+        else {
+            return;
+        };
+        let mut current = self.server_version.lock();
+        if current.as_deref() != Some(version) {
+            *current = Some(version.to_string());
+            self.server_version_changed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn fetch_once(
+        &self,
+        cursor: Option<&str>,
+        batch_size: usize,
     ) -> Result<Vec<JournalEntry>, JournalError> {
         let (url, range) = self.build_fetch_parts(cursor, batch_size);
+        // Held until the body has been read, which is when the connection frees up
+        let _permit = self.acquire_permit();
 
         debug!(url = %url, "Fetching journal entries");
 
-        let response = self
-            .client
-            .get(&url)
-            .header("Accept", "application/json")
-            .header("Range", range)
-            .send()?;
+        let response = match self.send_request(&url, &range) {
+            Err(e) if self.should_fall_back(&e) => {
+                warn!(
+                    url = %self.base_url,
+                    error = %e,
+                    "HTTP/2 prior knowledge request failed, falling back to HTTP/1.1"
+                );
+                self.http2_disabled.store(true, Ordering::Relaxed);
+                self.send_request(&url, &range)?
+            }
+            result => result?,
+        };
+        if self.http1_fallback.is_some() && !self.http2_disabled() {
+            self.http2_confirmed.store(true, Ordering::Relaxed);
+        }
 
         let status = response.status();
         trace!(status = %status, "Got response");
+        self.observe_server_version(response.headers());
 
         match status {
             StatusCode::OK => {
-                // Parse newline-delimited JSON
-                let body = response.text()?;
-                let entries = self.parse_entries(&body)?;
+                let entries = match self.options.format {
+                    JournalFormat::Json => self.parse_entries(&response.text()?)?,
+                    JournalFormat::Export => self.parse_export(&response.bytes()?)?,
+                };
                 Ok(self.strip_seen_cursor(entries, cursor))
             }
             StatusCode::NO_CONTENT => {
                 debug!("No new entries");
                 Ok(Vec::new())
             }
-            StatusCode::GONE => {
-                warn!("Cursor is no longer valid (410 Gone)");
-                Err(JournalError::InvalidCursor)
+            _ => Err(status_error(status)),
+        }
+    }
+
+    /// Stream entries after `cursor` from a `follow` request, returning once
+    /// `batch_size` entries arrived, entries have been held for
+    /// `FOLLOW_FLUSH_INTERVAL`, the stream idled for `FOLLOW_MAX_WAIT`,
+    /// gatewayd closed it or `shutdown` was set.
+    ///
+    /// Returning regularly lets the caller forward and checkpoint the cursor;
+    /// the next call reopens the stream from there. Failures to open the
+    /// stream are not retried. A stream that breaks off after opening returns
+    /// the entries read so far, marked `dropped`, so the caller can reconnect.
+    pub fn follow(
+        &self,
+        cursor: Option<&str>,
+        batch_size: usize,
+        shutdown: &AtomicBool,
+    ) -> Result<FollowedEntries, JournalError> {
+        let (url, range) = self.build_fetch_parts(cursor, batch_size);
+        let _permit = self.acquire_permit();
+
+        debug!(url = %url, "Following journal entries");
+
+        let client = self.follow_client.as_ref().unwrap_or(&self.client);
+        let response = match self
+            .get(client, &url)
+            .header("Accept", self.options.format.accept())
+            .header("Range", &range)
+            .send()
+        {
+            // gatewayd may hold the headers until the first entry
+            Err(e) if e.is_timeout() => return Ok(FollowedEntries::default()),
+            result => result?,
+        };
+
+        let status = response.status();
+        trace!(status = %status, "Got follow response");
+        self.observe_server_version(response.headers());
+        match status {
+            StatusCode::OK => {}
+            StatusCode::NO_CONTENT => return Ok(FollowedEntries::default()),
+            _ => return Err(status_error(status)),
+        }
+
+        // Only a framed body can end cleanly; EOF on one delimited by the
+        // connection closing can't be told apart from a dropped connection.
+        let framed = response.version() >= Version::HTTP_2
+            || response.content_length().is_some()
+            || response
+                .headers()
+                .get(TRANSFER_ENCODING)
+                .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"chunked"));
+        let mut dropped = false;
+        let started = Instant::now();
+        let mut reader = BufReader::new(response);
+        let mut line = Vec::new();
+        let mut entries = Vec::new();
+        while entries.len() < batch_size && !shutdown.load(Ordering::Relaxed) {
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => {
+                    dropped = !framed || !line.is_empty();
+                    break;
+                }
+                Ok(_) => {
+                    // A timed-out read keeps its partial line in `line`
+                    if let Some(entry) = self.parse_line(&String::from_utf8_lossy(&line)) {
+                        entries.push(entry);
+                    }
+                    line.clear();
+                }
+                Err(e) if is_read_timeout(&e) => {}
+                Err(e) => {
+                    debug!(url = %url, error = %e, "Follow stream read failed");
+                    dropped = true;
+                    break;
+                }
+            }
+            let waited = started.elapsed();
+            if waited >= FOLLOW_MAX_WAIT || (!entries.is_empty() && waited >= FOLLOW_FLUSH_INTERVAL)
+            {
+                break;
+            }
+        }
+
+        if dropped {
+            debug!(url = %url, count = entries.len(), "Follow stream dropped");
+        } else {
+            debug!(count = entries.len(), "Read followed journal entries");
+        }
+        Ok(FollowedEntries {
+            entries: self.strip_seen_cursor(entries, cursor),
+            dropped,
+        })
+    }
+
+    fn http2_disabled(&self) -> bool {
+        self.http2_disabled.load(Ordering::Relaxed)
+    }
+
+    /// Whether a failed h2c request means the server does not speak h2c:
+    /// none has succeeded yet and the connection was made but the HTTP/2
+    /// session broke down, as it does when an HTTP/1.1 server gets the
+    /// preface. Refused connections and timeouts keep h2c.
+    fn should_fall_back(&self, e: &reqwest::Error) -> bool {
+        if self.http1_fallback.is_none()
+            || self.http2_disabled()
+            || self.http2_confirmed.load(Ordering::Relaxed)
+            || e.is_connect()
+            || e.is_timeout()
+        {
+            return false;
+        }
+        let mut source = std::error::Error::source(e);
+        while let Some(err) = source {
+            if err.is::<hyper::Error>() {
+                return true;
             }
-            _ => Err(JournalError::ServerError { status }),
+            source = err.source();
         }
+        false
+    }
+
+    /// Issue the GET on the active client (the HTTP/1.1 fallback once h2c failed).
+    fn send_request(
+        &self,
+        url: &str,
+        range: &str,
+    ) -> Result<reqwest::blocking::Response, reqwest::Error> {
+        self.request(url, range).send()
+    }
+
+    /// Build the entries GET on the active client.
+    fn request(&self, url: &str, range: &str) -> reqwest::blocking::RequestBuilder {
+        let client = match &self.http1_fallback {
+            Some(fallback) if self.http2_disabled() => fallback,
+            _ => &self.client,
+        };
+        self.get(client, url)
+            .header("Accept", self.options.format.accept())
+            .header("Range", range)
     }
 
     /// Parse newline-delimited JSON entries
@@ -296,24 +1067,127 @@ impl JournalClient {
         let mut entries = Vec::new();
 
         for line in body.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
+            if let Some(entry) = self.parse_line(line) {
+                entries.push(entry);
+            }
+        }
+
+        debug!(count = entries.len(), "Parsed journal entries");
+        Ok(entries)
+    }
+
+    /// Normalize extra field names, then apply the field filter.
+    fn shape_fields(&self, entry: &mut JournalEntry) {
+        if let Some(rules) = &self.options.field_normalization {
+            entry.normalize_field_names(rules);
+        }
+        if !self.options.field_filter.is_empty() {
+            entry.filter_fields(&self.options.field_filter);
+        }
+    }
+
+    /// Parse one JSON line; blank or unparseable lines yield `None`.
+    fn parse_line(&self, line: &str) -> Option<JournalEntry> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+
+        match serde_json::from_str::<RawJournalEntry>(line) {
+            Ok(raw) => {
+                let mut entry = JournalEntry::from_raw(raw, self.options.max_field_bytes);
+                if self.options.keep_raw {
+                    entry.raw = Some(line.to_string());
+                }
+                self.shape_fields(&mut entry);
+                Some(entry)
+            }
+            Err(e) => {
+                warn!(error = %e, line = %line.chars().take(100).collect::<String>(), "Failed to parse journal entry, skipping");
+                None
             }
+        }
+    }
+
+    /// Parse the journal export format: per entry, `FIELD=value` lines, or a
+    /// `FIELD` line followed by a little-endian u64 length, the raw value and
+    /// a newline; a blank line ends the entry.
+    ///
+    /// Fields are mapped onto the same JSON shape gatewayd serves (binary
+    /// values as byte arrays) so both formats decode identically.
+    fn parse_export(&self, body: &[u8]) -> Result<Vec<JournalEntry>, JournalError> {
+        let mut entries = Vec::new();
+        let mut fields = serde_json::Map::new();
+        let mut rest = body;
+
+        loop {
+            let line_end = rest.iter().position(|&b| b == b'\n');
+            let line = &rest[..line_end.unwrap_or(rest.len())];
+            rest = line_end.map_or(&[][..], |end| &rest[end + 1..]);
 
-            match serde_json::from_str::<RawJournalEntry>(line) {
-                Ok(raw) => {
-                    entries.push(JournalEntry::from_raw(raw, self.max_field_bytes));
+            if line.is_empty() {
+                if !fields.is_empty() {
+                    self.push_export_entry(std::mem::take(&mut fields), &mut entries);
                 }
-                Err(e) => {
-                    warn!(error = %e, line = %line.chars().take(100).collect::<String>(), "Failed to parse journal entry, skipping");
+                if rest.is_empty() {
+                    break;
                 }
+                continue;
+            }
+
+            if let Some(eq) = line.iter().position(|&b| b == b'=') {
+                let name = String::from_utf8_lossy(&line[..eq]).into_owned();
+                let value = String::from_utf8_lossy(&line[eq + 1..]).into_owned();
+                fields.insert(name, serde_json::Value::String(value));
+                continue;
             }
+
+            // Binary field: name line, 8-byte LE length, value, newline
+            let name = String::from_utf8_lossy(line).into_owned();
+            let Some(len_bytes) = rest.get(..8) else {
+                return Err(JournalError::Export(format!(
+                    "truncated length for field {}",
+                    name
+                )));
+            };
+            let len = u64::from_le_bytes(len_bytes.try_into().expect("8-byte slice")) as usize;
+            let Some(value) = rest.get(8..8usize.saturating_add(len)) else {
+                return Err(JournalError::Export(format!(
+                    "truncated value for field {}",
+                    name
+                )));
+            };
+            let value = match std::str::from_utf8(value) {
+                Ok(text) => serde_json::Value::String(text.to_string()),
+                Err(_) => serde_json::Value::from(value.to_vec()),
+            };
+            fields.insert(name, value);
+            rest = rest.get(8 + len + 1..).unwrap_or_default();
         }
 
-        debug!(count = entries.len(), "Parsed journal entries");
+        debug!(count = entries.len(), "Parsed journal export entries");
         Ok(entries)
     }
+
+    fn push_export_entry(
+        &self,
+        fields: serde_json::Map<String, serde_json::Value>,
+        entries: &mut Vec<JournalEntry>,
+    ) {
+        let fields = serde_json::Value::Object(fields);
+        let raw = self.options.keep_raw.then(|| fields.to_string());
+        match serde_json::from_value::<RawJournalEntry>(fields) {
+            Ok(parsed) => {
+                let mut entry = JournalEntry::from_raw(parsed, self.options.max_field_bytes);
+                entry.raw = raw;
+                self.shape_fields(&mut entry);
+                entries.push(entry);
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to parse journal export entry, skipping");
+            }
+        }
+    }
 }
 
 // URL encoding helper
@@ -343,6 +1217,7 @@ impl From<RawJournalEntry> for JournalEntry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{serve, serve_once};
 
     #[test]
     fn test_parse_entry() {
@@ -355,6 +1230,78 @@ mod tests {
         assert_eq!(entry.message, "Hello world");
         assert_eq!(entry.priority, Some(6));
         assert_eq!(entry.systemd_unit, Some("test.service".to_string()));
+        assert!(!entry.priority_out_of_range());
+    }
+
+    #[test]
+    fn test_normalize_field_names() {
+        let json = r#"{"__CURSOR":"s=abc;i=1","__REALTIME_TIMESTAMP":"1","MESSAGE":"m","container-name":"b","container_name":"a","CONTAINER_NAME":"canonical","req.id":"7"}"#;
+        let mut entry = JournalEntry::from(serde_json::from_str::<RawJournalEntry>(json).unwrap());
+        let rules: FieldNormalization = toml::from_str("").unwrap();
+        entry.normalize_field_names(&rules);
+        assert_eq!(
+            entry.extra_fields,
+            HashMap::from([
+                ("CONTAINER_NAME".to_string(), "canonical".to_string()),
+                ("REQ_ID".to_string(), "7".to_string()),
+            ])
+        );
+
+        // Without the canonical spelling, the first name in sort order wins
+        entry.extra_fields = HashMap::from([
+            ("container_name".to_string(), "a".to_string()),
+            ("container-name".to_string(), "b".to_string()),
+        ]);
+        entry.normalize_field_names(&rules);
+        assert_eq!(entry.extra_fields["CONTAINER_NAME"], "b");
+    }
+
+    #[test]
+    fn test_field_filter() {
+        let options = JournalOptions {
+            field_normalization: Some(toml::from_str("").unwrap()),
+            field_filter: FieldFilter {
+                include: vec!["TRACE_ID".to_string(), "REQ_ID".to_string()],
+                exclude: vec!["REQ_ID".to_string()],
+            },
+            ..Default::default()
+        };
+        let client =
+            JournalClient::new("http://h:19531", vec![], None, &HashMap::new(), options).unwrap();
+        let entry = client
+            .parse_line(r#"{"__CURSOR":"s=abc;i=1","__REALTIME_TIMESTAMP":"1","MESSAGE":"m","PRIORITY":"","_SYSTEMD_UNIT":"a.service","trace-id":"t","REQ_ID":"7","_SOURCE_REALTIME_TIMESTAMP":"1"}"#)
+            .unwrap();
+        // Included fields match after normalization, exclude wins, and
+        // dedicated fields and an unparseable PRIORITY are kept
+        assert_eq!(
+            entry.extra_fields,
+            HashMap::from([
+                ("TRACE_ID".to_string(), "t".to_string()),
+                ("PRIORITY".to_string(), String::new()),
+            ])
+        );
+        assert_eq!(entry.systemd_unit.as_deref(), Some("a.service"));
+        assert_eq!(entry.message, "m");
+    }
+
+    #[test]
+    fn test_parse_out_of_range_priority() {
+        let parse = |priority: &str| {
+            let json = format!(
+                r#"{{"__CURSOR":"s=abc;i=1","__REALTIME_TIMESTAMP":"1","MESSAGE":"x","PRIORITY":"{}"}}"#,
+                priority
+            );
+            JournalEntry::from(serde_json::from_str::<RawJournalEntry>(&json).unwrap())
+        };
+
+        let entry = parse("8");
+        assert_eq!(entry.priority, Some(8));
+        assert!(entry.priority_out_of_range());
+
+        let entry = parse("");
+        assert_eq!(entry.priority, None);
+        assert_eq!(entry.field("PRIORITY"), Some(""));
+        assert!(entry.priority_out_of_range());
     }
 
     #[test]
@@ -364,6 +1311,13 @@ mod tests {
         let entry = JournalEntry::from(raw);
 
         assert_eq!(entry.message, "Hello");
+        assert!(entry.message_bytes.is_none());
+
+        let json = r#"{"__CURSOR":"s=abc;i=1","__REALTIME_TIMESTAMP":"1703456789000000","MESSAGE":[72,105,255]}"#;
+        let raw: RawJournalEntry = serde_json::from_str(json).unwrap();
+        let entry = JournalEntry::from(raw);
+        assert_eq!(entry.message, "Hi\u{fffd}");
+        assert_eq!(entry.message_bytes, Some(vec![72, 105, 255]));
     }
 
     #[test]
@@ -379,7 +1333,7 @@ mod tests {
             vec![],
             None,
             &HashMap::new(),
-            1024,
+            JournalOptions::default(),
         )
         .unwrap();
         let (url, range) = client.build_fetch_parts(None, 500);
@@ -388,14 +1342,37 @@ mod tests {
     }
 
     #[test]
-    fn test_build_fetch_parts_cursor_in_range_header() {
+    fn test_build_fetch_parts_start_timestamp() {
+        let options = JournalOptions {
+            start_timestamp: Some(UNIX_EPOCH + Duration::from_secs(1714564800)),
+            ..Default::default()
+        };
         let client =
-            JournalClient::new("http://host:19531", vec![], None, &HashMap::new(), 1024).unwrap();
-        let cursor = "s=abc;i=1f;b=xyz;m=123;t=456;x=deadbeef";
-        let (url, range) = client.build_fetch_parts(Some(cursor), 100);
-        assert!(
-            !url.contains("cursor"),
-            "cursor must not leak into URL: {}",
+            JournalClient::new("http://h:19531", vec![], None, &HashMap::new(), options).unwrap();
+        let (url, range) = client.build_fetch_parts(None, 500);
+        assert_eq!(url, "http://h:19531/entries");
+        assert_eq!(range, "realtime=1714564800::0:500");
+
+        // An established cursor always wins
+        let (_, range) = client.build_fetch_parts(Some("s=abc;i=1"), 500);
+        assert_eq!(range, "entries=s=abc;i=1:1:500");
+    }
+
+    #[test]
+    fn test_build_fetch_parts_cursor_in_range_header() {
+        let client = JournalClient::new(
+            "http://host:19531",
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
+        let cursor = "s=abc;i=1f;b=xyz;m=123;t=456;x=deadbeef";
+        let (url, range) = client.build_fetch_parts(Some(cursor), 100);
+        assert!(
+            !url.contains("cursor"),
+            "cursor must not leak into URL: {}",
             url
         );
         assert!(
@@ -407,6 +1384,604 @@ mod tests {
         assert_eq!(range, format!("entries={}:1:100", cursor));
     }
 
+    #[test]
+    fn test_build_fetch_parts_without_cursor_skip() {
+        let options = JournalOptions {
+            cursor_skip: false,
+            ..Default::default()
+        };
+        let client =
+            JournalClient::new("http://h:19531", vec![], None, &HashMap::new(), options).unwrap();
+        let (_, range) = client.build_fetch_parts(Some("s=abc;i=1"), 100);
+        assert_eq!(range, "entries=s=abc;i=1:0:101");
+    }
+
+    #[test]
+    fn test_build_fetch_parts_follow() {
+        let options = JournalOptions {
+            follow: true,
+            ..Default::default()
+        };
+        let client = JournalClient::new(
+            "http://h:19531",
+            vec!["nginx.service".to_string()],
+            None,
+            &HashMap::new(),
+            options,
+        )
+        .unwrap();
+        let (url, _) = client.build_fetch_parts(None, 10);
+        assert_eq!(
+            url,
+            "http://h:19531/entries?boot&follow&_SYSTEMD_UNIT=nginx.service"
+        );
+        let (url, range) = client.build_fetch_parts(Some("s=abc;i=1"), 10);
+        assert_eq!(
+            url,
+            "http://h:19531/entries?follow&_SYSTEMD_UNIT=nginx.service"
+        );
+        assert_eq!(range, "entries=s=abc;i=1:1:10");
+    }
+
+    #[test]
+    fn test_follow_returns_streamed_entries_while_open() {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(
+                concat!(
+                    "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n",
+                    r#"{"__CURSOR":"s=abc;i=1","__REALTIME_TIMESTAMP":"1","MESSAGE":"seen"}"#,
+                    "\n",
+                    r#"{"__CURSOR":"s=abc;i=2","__REALTIME_TIMESTAMP":"2","MESSAGE":"new"}"#,
+                    "\n",
+                )
+                .as_bytes(),
+            );
+            // Hold the stream open like gatewayd waiting for new entries
+            std::thread::sleep(Duration::from_secs(5));
+        });
+
+        let options = JournalOptions {
+            follow: true,
+            cursor_skip: false,
+            ..Default::default()
+        };
+        let client = JournalClient::new(
+            &format!("http://{}", addr),
+            vec![],
+            None,
+            &HashMap::new(),
+            options,
+        )
+        .unwrap();
+        let started = Instant::now();
+        let followed = client
+            .follow(Some("s=abc;i=1"), 100, &AtomicBool::new(false))
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert!(!followed.dropped);
+        let entries = followed.entries;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "new");
+    }
+
+    #[test]
+    fn test_follow_waits_for_entries_past_read_timeout() {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n");
+            let _ = stream.flush();
+            // The next entry arrives well after a single read times out
+            std::thread::sleep(Duration::from_millis(2500));
+            let _ = stream.write_all(
+                concat!(
+                    r#"{"__CURSOR":"s=abc;i=2","__REALTIME_TIMESTAMP":"2","MESSAGE":"late"}"#,
+                    "\n",
+                )
+                .as_bytes(),
+            );
+            std::thread::sleep(Duration::from_secs(5));
+        });
+
+        let options = JournalOptions {
+            follow: true,
+            ..Default::default()
+        };
+        let client = JournalClient::new(
+            &format!("http://{}", addr),
+            vec![],
+            None,
+            &HashMap::new(),
+            options,
+        )
+        .unwrap();
+        let entries = client
+            .follow(None, 100, &AtomicBool::new(false))
+            .unwrap()
+            .entries;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "late");
+    }
+
+    #[test]
+    fn test_follow_tells_dropped_stream_from_clean_end() {
+        let follow = |response: &str| {
+            let options = JournalOptions {
+                follow: true,
+                ..Default::default()
+            };
+            JournalClient::new(
+                &serve_once(response.to_string()),
+                vec![],
+                None,
+                &HashMap::new(),
+                options,
+            )
+            .unwrap()
+            .follow(None, 100, &AtomicBool::new(false))
+            .unwrap()
+        };
+        let line = concat!(
+            r#"{"__CURSOR":"s=abc;i=1","__REALTIME_TIMESTAMP":"1","MESSAGE":"a"}"#,
+            "\n"
+        );
+
+        // gatewayd ended the chunked body
+        let ended = follow(&format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            line.len(),
+            line
+        ));
+        assert!(!ended.dropped);
+        assert_eq!(ended.entries.len(), 1);
+
+        // The connection closed with no framing to say the body was complete
+        let closed = follow(&format!(
+            "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}",
+            line
+        ));
+        assert!(closed.dropped);
+        assert_eq!(closed.entries.len(), 1);
+
+        // The connection closed inside a chunk
+        let truncated = follow(&format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}",
+            line.len() + 10,
+            line
+        ));
+        assert!(truncated.dropped);
+        assert_eq!(truncated.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_fetch_drops_cursor_entry_when_server_ignores_skip() {
+        let body = concat!(
+            r#"{"__CURSOR":"s=abc;i=1","__REALTIME_TIMESTAMP":"1","MESSAGE":"seen"}"#,
+            "\n",
+            r#"{"__CURSOR":"s=abc;i=2","__REALTIME_TIMESTAMP":"2","MESSAGE":"new"}"#,
+            "\n",
+        );
+        let url = serve_once(format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        ));
+        let client = JournalClient::new(
+            &url,
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
+        let entries = client.fetch(Some("s=abc;i=1"), 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "new");
+    }
+
+    #[test]
+    fn test_fetch_retries_server_errors() {
+        let body = r#"{"__CURSOR":"s=abc;i=2","__REALTIME_TIMESTAMP":"2","MESSAGE":"ok"}"#;
+        let (url, _) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        ]);
+        let options = JournalOptions {
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let client = JournalClient::new(&url, vec![], None, &HashMap::new(), options).unwrap();
+        let entries = client.fetch(None, 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(client.take_retries(), 1);
+        assert_eq!(client.take_retries(), 0);
+    }
+
+    #[test]
+    fn test_fetch_skips_retries_after_shutdown() {
+        let url = serve_once(
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        );
+        let options = JournalOptions {
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let mut client = JournalClient::new(&url, vec![], None, &HashMap::new(), options).unwrap();
+        client.set_shutdown(Arc::new(AtomicBool::new(true)));
+        assert!(matches!(
+            client.fetch(None, 10),
+            Err(JournalError::ServerError { .. })
+        ));
+        assert_eq!(client.take_retries(), 0);
+    }
+
+    #[test]
+    fn test_retry_backoff_wakes_on_shutdown() {
+        let url = serve_once(
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        );
+        let options = JournalOptions {
+            max_attempts: 3,
+            retry_backoff: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let mut client = JournalClient::new(&url, vec![], None, &HashMap::new(), options).unwrap();
+        client.set_shutdown(shutdown.clone());
+        let raise = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            shutdown.store(true, Ordering::Relaxed);
+        });
+        let started = std::time::Instant::now();
+        assert!(matches!(
+            client.fetch(None, 10),
+            Err(JournalError::ServerError { .. })
+        ));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        raise.join().unwrap();
+    }
+
+    #[test]
+    fn test_hmac_signer() {
+        let signer = RequestSigner::hmac_sha256(&HmacConfig {
+            header: "X-Signature".to_string(),
+            secret: "s3cret".to_string(),
+            timestamp_header: "X-Timestamp".to_string(),
+        });
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let headers = (signer.0)("http://h:19531/entries?_SYSTEMD_UNIT=a.service", now);
+        assert_eq!(
+            headers,
+            vec![
+                ("X-Timestamp".to_string(), "1700000000".to_string()),
+                (
+                    "X-Signature".to_string(),
+                    "23dc9251558dff669faa0a7479cc807f11d26b4af00c017fa2fdcd6b9ab10c7a".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_request_signer_headers_sent() {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+            }
+        });
+
+        let client = JournalClient::new(
+            &format!("http://{}", addr),
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap()
+        .with_request_signer(RequestSigner::new(|url, _| {
+            vec![("x-signed-path".to_string(), path_and_query(url))]
+        }));
+        client.fetch(None, 10).unwrap();
+        assert!(rx.recv().unwrap().contains("x-signed-path: /entries"));
+    }
+
+    #[test]
+    fn test_probe() {
+        // Any HTTP response means gatewayd is listening
+        let url = serve_once(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        );
+        let client = JournalClient::new(
+            &url,
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
+        assert!(client.probe().is_ok());
+
+        // Nothing listening on a port we just released
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let client = JournalClient::new(
+            &format!("http://{}", addr),
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
+        assert!(matches!(client.probe(), Err(JournalError::Http(_))));
+    }
+
+    #[test]
+    fn test_server_version_change() {
+        let no_content = |server: &str| {
+            format!(
+                "HTTP/1.1 204 No Content\r\nServer: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                server
+            )
+        };
+        let (url, _) = serve(vec![
+            no_content("systemd-journal-gatewayd/254"),
+            no_content("systemd-journal-gatewayd/254"),
+            no_content("systemd-journal-gatewayd/256"),
+        ]);
+        let client = JournalClient::new(
+            &url,
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(client.take_server_version_change(), None);
+
+        client.fetch(None, 10).unwrap();
+        assert_eq!(
+            client.take_server_version_change().as_deref(),
+            Some("systemd-journal-gatewayd/254")
+        );
+        client.fetch(None, 10).unwrap();
+        assert_eq!(client.take_server_version_change(), None);
+        client.fetch(None, 10).unwrap();
+        assert_eq!(
+            client.take_server_version_change().as_deref(),
+            Some("systemd-journal-gatewayd/256")
+        );
+    }
+
+    #[test]
+    fn test_fetch_retries_empty_responses() {
+        let no_content =
+            "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let body = r#"{"__CURSOR":"s=abc;i=1","__REALTIME_TIMESTAMP":"1","MESSAGE":"late"}"#;
+        let ok = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let options = JournalOptions {
+            retry_empty: 2,
+            retry_empty_delay: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let (url, _) = serve(vec![no_content.to_string(), no_content.to_string(), ok]);
+        let client =
+            JournalClient::new(&url, vec![], None, &HashMap::new(), options.clone()).unwrap();
+        assert_eq!(client.fetch(None, 10).unwrap().len(), 1);
+
+        // Gives up once the refetches are used up
+        let (url, _) = serve(vec![no_content.to_string(); 3]);
+        let client = JournalClient::new(&url, vec![], None, &HashMap::new(), options).unwrap();
+        assert!(client.fetch(None, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_with_server_name() {
+        let (url, addrs) = with_server_name("https://10.0.0.5:19531/", "gw-a.example.com").unwrap();
+        assert_eq!(url, "https://gw-a.example.com:19531/");
+        assert_eq!(addrs, vec!["10.0.0.5:19531".parse().unwrap()]);
+
+        let (url, addrs) = with_server_name("https://[fd00::5]", "gw-b.example.com").unwrap();
+        assert_eq!(url, "https://gw-b.example.com/");
+        assert_eq!(addrs, vec!["[fd00::5]:443".parse().unwrap()]);
+    }
+
+    #[test]
+    fn test_source_policy_pins_and_blocks_redirects() {
+        let policy = SourcePolicy::default();
+        let options = JournalOptions {
+            source_policy: Some(policy.clone()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            JournalClient::new(
+                "http://127.0.0.1:19531",
+                vec![],
+                None,
+                &HashMap::new(),
+                options
+            ),
+            Err(JournalError::Config(_))
+        ));
+        let options = JournalOptions {
+            tls_server_name: Some("gw.example.com".to_string()),
+            source_policy: Some(policy),
+            ..Default::default()
+        };
+        assert!(
+            JournalClient::new(
+                "https://10.0.0.5:19531",
+                vec![],
+                None,
+                &HashMap::new(),
+                options
+            )
+            .is_err()
+        );
+
+        let entry = r#"{"__CURSOR":"c1","__REALTIME_TIMESTAMP":"1","MESSAGE":"x"}"#;
+        let (url, _) = serve(vec![
+            "HTTP/1.1 302 Found\r\nLocation: /entries\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}\n",
+                entry.len() + 1,
+                entry
+            ),
+        ]);
+        let options = JournalOptions {
+            source_policy: Some(SourcePolicy {
+                allow: vec!["127.0.0.1".parse().unwrap()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let client = JournalClient::new(&url, vec![], None, &HashMap::new(), options).unwrap();
+        assert!(matches!(
+            client.fetch(None, 10),
+            Err(JournalError::ServerError { status }) if status == StatusCode::FOUND
+        ));
+    }
+
+    #[test]
+    fn test_fetch_does_not_retry_gone() {
+        let (url, _) = serve(vec![
+            "HTTP/1.1 410 Gone\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        ]);
+        let options = JournalOptions {
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let client = JournalClient::new(&url, vec![], None, &HashMap::new(), options).unwrap();
+        assert!(matches!(
+            client.fetch(Some("s=abc;i=1"), 10),
+            Err(JournalError::InvalidCursor)
+        ));
+        assert_eq!(client.take_retries(), 0);
+    }
+
+    #[test]
+    fn test_fetch_unauthorized() {
+        let (url, _) = serve(vec![
+            "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        ]);
+        let options = JournalOptions {
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let client = JournalClient::new(&url, vec![], None, &HashMap::new(), options).unwrap();
+        assert!(matches!(
+            client.fetch(None, 10),
+            Err(JournalError::Unauthorized {
+                status: StatusCode::UNAUTHORIZED
+            })
+        ));
+        assert_eq!(client.take_retries(), 0);
+    }
+
+    #[test]
+    fn test_max_connections_blocks_until_released() {
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicBool;
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        let client = Arc::new(
+            JournalClient::new(
+                "http://h:19531",
+                vec![],
+                None,
+                &HashMap::new(),
+                JournalOptions::default(),
+            )
+            .unwrap()
+            .with_in_flight_observer(Box::new(move |n| recorder.lock().push(n))),
+        );
+
+        let permit = client.acquire_permit();
+        let acquired = Arc::new(AtomicBool::new(false));
+        let waiter = {
+            let (client, acquired) = (client.clone(), acquired.clone());
+            std::thread::spawn(move || {
+                let _permit = client.acquire_permit();
+                acquired.store(true, Ordering::SeqCst);
+            })
+        };
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!acquired.load(Ordering::SeqCst));
+        drop(permit);
+        waiter.join().unwrap();
+        assert!(acquired.load(Ordering::SeqCst));
+        assert_eq!(*seen.lock(), vec![1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_max_connections_is_per_source() {
+        let client = || {
+            JournalClient::new(
+                "http://gw:19531",
+                vec![],
+                None,
+                &HashMap::new(),
+                JournalOptions::default(),
+            )
+            .unwrap()
+        };
+        let (a, b) = (client(), client());
+        // Sources on the same gatewayd don't wait on each other
+        let _held = a.acquire_permit();
+        drop(b.acquire_permit());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let mut backoff = Backoff::new(
+            Duration::from_millis(500),
+            MAX_RETRY_DELAY,
+            RetryJitter::None,
+        );
+        assert_eq!(backoff.delay(1), Duration::from_millis(500));
+        assert_eq!(backoff.delay(2), Duration::from_secs(1));
+        assert_eq!(backoff.delay(3), Duration::from_secs(2));
+        assert_eq!(backoff.delay(40), MAX_RETRY_DELAY);
+    }
+
     #[test]
     fn test_build_fetch_parts_with_units() {
         let client = JournalClient::new(
@@ -414,7 +1989,7 @@ mod tests {
             vec!["nginx.service".to_string()],
             None,
             &HashMap::new(),
-            1024,
+            JournalOptions::default(),
         )
         .unwrap();
         let cursor = "s=abc;i=1";
@@ -430,7 +2005,7 @@ mod tests {
             vec!["my unit.service".to_string()],
             None,
             &HashMap::new(),
-            1024,
+            JournalOptions::default(),
         )
         .unwrap();
         let (url, _) = client.build_fetch_parts(None, 10);
@@ -441,9 +2016,101 @@ mod tests {
     }
 
     #[test]
-    fn test_strip_seen_cursor_removes_only_cursor() {
+    fn test_build_fetch_parts_ipv6_literal() {
+        let client = JournalClient::new(
+            "http://[fe80::1]:19531/",
+            vec!["nginx.service".to_string()],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
+        let (url, range) = client.build_fetch_parts(None, 10);
+        assert_eq!(
+            url,
+            "http://[fe80::1]:19531/entries?boot&_SYSTEMD_UNIT=nginx.service"
+        );
+        assert_eq!(range, "entries=:10");
+        assert_eq!(
+            reqwest::Url::parse(&url).unwrap().host_str(),
+            Some("[fe80::1]")
+        );
+
+        let (url, _) = client.build_fetch_parts(Some("s=abc;i=1"), 10);
+        assert_eq!(
+            url,
+            "http://[fe80::1]:19531/entries?_SYSTEMD_UNIT=nginx.service"
+        );
+    }
+
+    #[test]
+    fn test_http2_fallback_only_for_plain_http() {
+        let http2 = || JournalOptions {
+            http2: true,
+            ..Default::default()
+        };
+        let h2c =
+            JournalClient::new("http://h:19531", vec![], None, &HashMap::new(), http2()).unwrap();
+        assert!(h2c.http1_fallback.is_some());
+
+        let alpn =
+            JournalClient::new("https://h:19531", vec![], None, &HashMap::new(), http2()).unwrap();
+        assert!(alpn.http1_fallback.is_none());
+
+        let http1 = JournalClient::new(
+            "http://h:19531",
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
+        assert!(http1.http1_fallback.is_none());
+    }
+
+    #[test]
+    fn test_http2_fallback_only_on_h2c_rejection() {
+        let http2 = JournalOptions {
+            http2: true,
+            ..Default::default()
+        };
+        // Nothing listening: a connect error says nothing about h2c support
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let url = format!("http://{}", addr);
         let client =
-            JournalClient::new("http://h:19531", vec![], None, &HashMap::new(), 1024).unwrap();
+            JournalClient::new(&url, vec![], None, &HashMap::new(), http2.clone()).unwrap();
+        assert!(client.fetch(None, 10).is_err());
+        assert!(!client.http2_disabled());
+
+        // An HTTP/1.1 server answering the h2c preface
+        let entry = r#"{"__CURSOR":"c1","__REALTIME_TIMESTAMP":"1","MESSAGE":"x"}"#;
+        let (url, _) = serve(vec![
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}\n",
+                entry.len() + 1,
+                entry
+            ),
+        ]);
+        let client = JournalClient::new(&url, vec![], None, &HashMap::new(), http2).unwrap();
+        assert_eq!(client.fetch(None, 10).unwrap().len(), 1);
+        assert!(client.http2_disabled());
+    }
+
+    #[test]
+    fn test_strip_seen_cursor_removes_only_cursor() {
+        let client = JournalClient::new(
+            "http://h:19531",
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
         let entries = vec![
             JournalEntry {
                 cursor: "a".to_string(),
@@ -468,8 +2135,14 @@ mod tests {
 
     #[test]
     fn test_strip_seen_cursor_none_keeps_all() {
-        let client =
-            JournalClient::new("http://h:19531", vec![], None, &HashMap::new(), 1024).unwrap();
+        let client = JournalClient::new(
+            "http://h:19531",
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
         let entries = vec![JournalEntry {
             cursor: "a".to_string(),
             realtime_timestamp: 1,
@@ -479,6 +2152,72 @@ mod tests {
         assert_eq!(out.len(), 1);
     }
 
+    #[test]
+    fn test_parse_entries_keeps_raw_line() {
+        let line = r#"{"__CURSOR":"s=abc;i=1","__REALTIME_TIMESTAMP":"1703456789000000","MESSAGE":"Hi","CUSTOM":"x"}"#;
+        let options = JournalOptions {
+            keep_raw: true,
+            ..Default::default()
+        };
+        let client =
+            JournalClient::new("http://h:19531", vec![], None, &HashMap::new(), options).unwrap();
+        let entries = client.parse_entries(&format!("{}\n", line)).unwrap();
+        assert_eq!(entries[0].raw.as_deref(), Some(line));
+
+        let client = JournalClient::new(
+            "http://h:19531",
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
+        let entries = client.parse_entries(line).unwrap();
+        assert!(entries[0].raw.is_none());
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let mut body = b"__CURSOR=s=abc;i=1\n__REALTIME_TIMESTAMP=1703456789000000\nPRIORITY=6\n_SYSTEMD_UNIT=test.service\nMESSAGE=Hello\n\n".to_vec();
+        body.extend_from_slice(
+            b"__CURSOR=s=abc;i=2\n__REALTIME_TIMESTAMP=1703456789000001\nMESSAGE\n",
+        );
+        body.extend_from_slice(&10u64.to_le_bytes());
+        body.extend_from_slice(b"two\nlines\xff\n");
+        body.extend_from_slice(b"BLOB\n");
+        body.extend_from_slice(&2u64.to_le_bytes());
+        body.extend_from_slice(b"ok\n\n");
+
+        let client = JournalClient::new(
+            "http://h:19531",
+            vec![],
+            None,
+            &HashMap::new(),
+            JournalOptions::default(),
+        )
+        .unwrap();
+        let entries = client.parse_export(&body).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].cursor, "s=abc;i=1");
+        assert_eq!(entries[0].message, "Hello");
+        assert_eq!(entries[0].priority, Some(6));
+        assert_eq!(entries[0].systemd_unit.as_deref(), Some("test.service"));
+        assert_eq!(entries[1].realtime_timestamp, 1703456789000001);
+        assert_eq!(entries[1].message, "two\nlines\u{fffd}");
+        assert_eq!(
+            entries[1].message_bytes.as_deref(),
+            Some(&b"two\nlines\xff"[..])
+        );
+        assert_eq!(entries[1].field("BLOB"), Some("ok"));
+
+        // A length running past the body is an error, not a silent skip
+        let truncated = &body[..body.len() - 6];
+        assert!(matches!(
+            client.parse_export(truncated),
+            Err(JournalError::Export(_))
+        ));
+    }
+
     #[test]
     fn test_extra_field_truncation() {
         let max = 8;
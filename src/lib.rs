@@ -0,0 +1,436 @@
+//! otel-journal-gatewayd-forwarder
+//!
+//! Pull-based journal log forwarder. Collects logs from remote
+//! systemd-journal-gatewayd endpoints and forwards them to an OTLP-compatible backend.
+//!
+//! The binary is a thin wrapper around [`run_with`]; embedders can call
+//! [`run`] or [`run_with`] with their own shutdown flag, or drive a
+//! [`Collector`] directly.
+
+pub mod audit;
+pub mod backoff;
+pub mod benchmark;
+pub mod collector;
+pub mod config;
+pub mod cursor;
+pub mod journal;
+pub mod loki;
+pub mod metrics;
+pub mod otlp;
+pub mod policy;
+mod supervisor;
+#[cfg(test)]
+mod test_util;
+
+pub use collector::Collector;
+pub use config::Config;
+pub use journal::JournalClient;
+pub use otlp::OtlpClient;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+#[cfg(unix)]
+use sd_notify::NotifyState;
+
+/// Process-level options for [`run_with`] that are not part of the config file.
+#[derive(Debug, Default)]
+pub struct RunOptions {
+    /// Stop each collector once its source has caught up.
+    pub once: bool,
+    /// Raise the shutdown flag after this long.
+    pub max_runtime: Option<Duration>,
+    /// Serve Prometheus metrics on this address.
+    pub metrics_addr: Option<String>,
+    /// Also append forwarded records to this JSON Lines file.
+    pub debug_dump: Option<PathBuf>,
+    /// Pretty-print OTLP payloads in trace logs.
+    pub pretty_payload: bool,
+    /// Bump to have every collector flush its cursor and log its status.
+    pub status_requests: Arc<AtomicU64>,
+    /// Self-test cursor storage before starting collectors.
+    pub cursor_self_test: bool,
+    /// Custom per-record attribute hook, shared by all collectors.
+    pub enricher: Option<otlp::Enricher>,
+    /// Write OTLP JSON lines to stdout instead of the OTLP endpoint.
+    pub stdout_sink: bool,
+    /// Per-request gatewayd header hook for every source; replaces any
+    /// source's built-in `hmac` signer.
+    pub request_signer: Option<journal::RequestSigner>,
+    /// Start even if another process holds the cursor directory lock.
+    pub allow_shared_cursor_dir: bool,
+    /// Configuration file re-read whenever `reload_requests` is bumped;
+    /// `None` disables reloading.
+    pub config_path: Option<PathBuf>,
+    /// Bump to reload `config_path` and apply its source changes. Zero means
+    /// the config passed to [`run_with`]; any other value when the run starts
+    /// reloads straight away, so bumps made during startup are not lost.
+    pub reload_requests: Arc<AtomicU64>,
+    /// Send systemd `READY=1`, `WATCHDOG=1` and `STOPPING=1` notifications
+    /// for the process; leave off unless the forwarder is the service.
+    pub systemd_notify: bool,
+}
+
+/// Run the forwarder with default options until `shutdown` is raised.
+pub fn run(config: Config, shutdown: Arc<AtomicBool>) -> Result<(), Box<dyn std::error::Error>> {
+    run_with(config, shutdown, RunOptions::default())
+}
+
+/// Run the forwarder until `shutdown` is raised (or, with `once`, until every
+/// source has caught up).
+///
+/// The caller owns the shutdown flag and any signal handling. Collectors that
+/// have not stopped `shutdown_grace` after shutdown are left running and
+/// reported as an error, so the caller decides whether to exit.
+pub fn run_with(
+    config: Config,
+    shutdown: Arc<AtomicBool>,
+    options: RunOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        otlp_endpoint = %config.otlp_endpoint,
+        sources = config.sources.len(),
+        config_hash = %config.hash(),
+        "Starting forwarder"
+    );
+
+    cursor::check_writable(&config.cursor_dir)?;
+    // Held until run_with returns
+    let _cursor_lock = if options.allow_shared_cursor_dir {
+        warn!(cursor_dir = %config.cursor_dir.display(), "Cursor directory lock disabled");
+        None
+    } else {
+        Some(cursor::lock_dir(&config.cursor_dir)?)
+    };
+    if options.cursor_self_test {
+        let warnings = cursor::storage_warnings(&config.cursor_dir);
+        for warning in &warnings {
+            error!("Cursor storage self-test: {}", warning);
+        }
+        if warnings.is_empty() {
+            info!(cursor_dir = %config.cursor_dir.display(), "Cursor storage self-test passed");
+        }
+    }
+
+    if let Some(max_runtime) = options.max_runtime {
+        start_runtime_limit(max_runtime, shutdown.clone());
+    }
+
+    // Setup metrics if enabled
+    let metrics = if let Some(ref addr) = options.metrics_addr {
+        let state = Arc::new(metrics::MetricsState::new());
+        state.set_config_hash(config.hash());
+        metrics::start_server(addr, state.clone())?;
+        Some(state)
+    } else {
+        None
+    };
+
+    // Create shared OTLP client
+    let mut otlp_client = build_otlp_client(&config, options.enricher.clone())?;
+    if options.stdout_sink || config.stdout_sink() {
+        info!("Writing OTLP requests to stdout");
+        otlp_client = otlp_client.with_stdout();
+    }
+    if let Some(path) = &options.debug_dump {
+        warn!(
+            path = %path.display(),
+            "Debug dump enabled: every forwarded record is also written to disk"
+        );
+        let dump = otlp::DebugDump::open(path, otlp::DEBUG_DUMP_MAX_BYTES)?;
+        otlp_client = otlp_client.with_debug_dump(dump);
+    }
+    if let Some(m) = &metrics {
+        let m = m.clone();
+        otlp_client = otlp_client.with_serialize_observer(Box::new(move |source, duration| {
+            m.record_serialize_duration(source, duration)
+        }));
+    }
+    let otlp = Arc::new(
+        otlp_client
+            .with_pretty_payload_log(options.pretty_payload)
+            .with_shutdown_timeout(shutdown.clone(), config.shutdown_flush_timeout),
+    );
+
+    let filter_audit = match &config.filter_audit {
+        Some(audit) => Some(Arc::new(audit::FilterAudit::new(audit)?)),
+        None => None,
+    };
+
+    let startup_gate = config
+        .startup_concurrency
+        .filter(|&limit| limit < config.sources.len())
+        .map(|limit| {
+            info!(
+                startup_concurrency = limit,
+                sources = config.sources.len(),
+                "Ramping up collectors"
+            );
+            Arc::new(collector::StartupGate::new(limit, config.sources.len()))
+        });
+
+    let parts = supervisor::CollectorParts {
+        otlp,
+        metrics: metrics.clone(),
+        request_signer: options.request_signer.clone(),
+        filter_audit,
+        unacked_budget: config
+            .max_unacked_entries
+            .map(|limit| Arc::new(collector::UnackedBudget::new(limit))),
+        cursor_dir: config.cursor_dir.clone(),
+        cursor_store: match config.cursor_backend {
+            config::CursorBackend::File => None,
+            config::CursorBackend::Sqlite => Some(cursor::SqliteStore::open(&config.cursor_dir)?),
+        },
+    };
+
+    let mut collectors = Vec::new();
+    for source in config.sources.clone() {
+        let mut collector = parts.build(source, &config)?;
+        if let Some(gate) = &startup_gate {
+            collector = collector.with_startup_gate(gate.clone());
+        }
+        collectors.push(collector);
+    }
+
+    if let Some(wait) = config.startup_wait {
+        wait_for_sources(&collectors, wait, &shutdown)?;
+    }
+
+    // Raised by the first collector to complete a poll
+    let polled_ok = Arc::new(AtomicBool::new(false));
+    let all_sources_down = Arc::new(AtomicBool::new(false));
+    if config.fail_if_all_sources_down {
+        start_all_sources_down_check(
+            config.all_sources_down_grace,
+            polled_ok.clone(),
+            all_sources_down.clone(),
+            shutdown.clone(),
+        );
+    }
+
+    let shutdown_grace = config.shutdown_grace;
+    let all_sources_down_grace = config.all_sources_down_grace;
+    let mut supervisor = supervisor::Supervisor::new(
+        config,
+        parts,
+        shutdown.clone(),
+        polled_ok,
+        options.status_requests.clone(),
+        options.once,
+    );
+    // Start collector threads, each with a freshness tick
+    for collector in collectors {
+        supervisor.spawn(collector, None);
+    }
+
+    let systemd = options.systemd_notify.then(systemd_ready);
+
+    let reload = options
+        .config_path
+        .clone()
+        .map(|path| supervisor::ReloadSource {
+            path,
+            requests: options.reload_requests.clone(),
+        });
+    let stuck = supervisor.run(reload, systemd);
+
+    if all_sources_down.load(Ordering::Relaxed) {
+        return Err(format!(
+            "no source completed a poll within {}",
+            humantime::format_duration(all_sources_down_grace)
+        )
+        .into());
+    }
+
+    if !stuck.is_empty() {
+        error!(
+            grace_ms = shutdown_grace.as_millis(),
+            sources = ?stuck,
+            "Collectors did not stop within shutdown grace"
+        );
+        return Err(format!(
+            "collectors did not stop within shutdown_grace of {}: {}",
+            humantime::format_duration(shutdown_grace),
+            stuck.join(", ")
+        )
+        .into());
+    }
+
+    info!("All collectors stopped, exiting");
+    Ok(())
+}
+
+/// Build the OTLP client described by `config`: endpoint, TLS, headers,
+/// compression and payload mapping.
+pub(crate) fn build_otlp_client(
+    config: &Config,
+    enricher: Option<otlp::Enricher>,
+) -> Result<otlp::OtlpClient, otlp::OtlpError> {
+    let otlp_tls = config.otlp_effective_tls();
+    if otlp_tls.as_ref().is_some_and(|t| t.insecure_skip_verify) {
+        warn!(
+            otlp_endpoint = %config.otlp_endpoint,
+            "TLS certificate verification is disabled for the OTLP endpoint"
+        );
+    }
+    let headers = config
+        .otlp_request_headers()
+        .map_err(|e| otlp::OtlpError::Config(e.to_string()))?;
+    let client = otlp::OtlpClient::new(
+        &config.otlp_endpoint,
+        config.otlp_path.as_deref(),
+        otlp_tls.as_ref(),
+        &headers,
+        otlp::OtlpTimeouts {
+            connect: config.otlp_connect_timeout,
+            request: config.otlp_timeout,
+        },
+        otlp::PayloadOptions {
+            resource_fields: config.resource_fields.clone(),
+            format: config.payload_format,
+            protocol: config.otlp_protocol,
+            group_by: config.group_by.clone(),
+            service_name_fallback: config.service_name_fallback.clone(),
+            body_format: config.body_format,
+            binary_message: config.binary_message,
+            timestamp_source: config.timestamp_source,
+            attribute_types: config.attribute_types.clone(),
+            infer_int_fields: config.infer_int_fields,
+            os_type: config.os_type.clone(),
+            os_description: config.os_description.clone(),
+            os_version: config.os_version.clone(),
+            code_attributes: config.code_attributes,
+            severity_number: config.severity_number,
+            severity_text: config.severity_text,
+            max_extra_fields: config.max_extra_fields,
+            max_records_per_scope: config.max_records_per_scope,
+            instance_id: config.instance_id.clone(),
+            resource_attributes: config.resource_attributes.clone(),
+            enricher,
+        },
+    )?
+    .with_compression(config.otlp_compression)
+    .with_cursor_range(config.cursor_range);
+    Ok(match &config.otlp_request_id_header {
+        Some(header) => client.with_request_id_header(header.clone()),
+        None => client,
+    })
+}
+
+/// Probe every source's gatewayd in parallel until all are reachable, failing
+/// with the unreachable source names once `wait` expires.
+fn wait_for_sources(
+    collectors: &[collector::Collector],
+    wait: Duration,
+    shutdown: &AtomicBool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        wait = %humantime::format_duration(wait),
+        "Waiting for gatewayd sources to become reachable"
+    );
+    let unreachable: Vec<&str> = thread::scope(|scope| {
+        let probes: Vec<_> = collectors
+            .iter()
+            .map(|c| scope.spawn(move || (c.source_name(), c.wait_until_reachable(wait, shutdown))))
+            .collect();
+        probes
+            .into_iter()
+            .filter_map(|probe| match probe.join() {
+                Ok((_, true)) => None,
+                Ok((name, false)) => Some(name),
+                Err(e) => std::panic::resume_unwind(e),
+            })
+            .collect()
+    });
+
+    if unreachable.is_empty() || shutdown.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    Err(format!(
+        "gatewayd unreachable after startup_wait of {}: {}",
+        humantime::format_duration(wait),
+        unreachable.join(", ")
+    )
+    .into())
+}
+
+/// Notify systemd that the service is ready now that all collectors are
+/// spawned, and read the watchdog timeout if it is enabled.
+fn systemd_ready() -> supervisor::Systemd {
+    #[cfg(unix)]
+    {
+        if let Err(e) = sd_notify::notify(false, &[NotifyState::Ready]) {
+            warn!(error = %e, "Failed to send systemd ready notification");
+        } else {
+            info!("Sent systemd ready notification");
+        }
+
+        let mut usec = 0u64;
+        let watchdog = sd_notify::watchdog_enabled(false, &mut usec).then(|| {
+            let timeout = Duration::from_micros(usec);
+            info!(timeout_ms = timeout.as_millis(), "systemd watchdog enabled");
+            timeout
+        });
+        supervisor::Systemd { watchdog }
+    }
+    #[cfg(not(unix))]
+    supervisor::Systemd { watchdog: None }
+}
+
+/// Raise the shutdown flag once `limit` has elapsed so collectors stop at their
+/// next checkpoint (between batches) with cursors persisted.
+fn start_runtime_limit(limit: Duration, shutdown: Arc<AtomicBool>) {
+    let deadline = std::time::Instant::now() + limit;
+    thread::spawn(move || {
+        while !shutdown.load(Ordering::Relaxed) {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                warn!(
+                    max_runtime = %humantime::format_duration(limit),
+                    "Maximum runtime reached, shutting down"
+                );
+                shutdown.store(true, Ordering::Relaxed);
+                break;
+            }
+            thread::sleep((deadline - now).min(Duration::from_millis(100)));
+        }
+    });
+}
+
+/// Shut down and flag `all_down` if no collector has completed a poll once
+/// `grace` has elapsed.
+fn start_all_sources_down_check(
+    grace: Duration,
+    polled_ok: Arc<AtomicBool>,
+    all_down: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
+) {
+    let deadline = std::time::Instant::now() + grace;
+    thread::spawn(move || {
+        while !shutdown.load(Ordering::Relaxed) && !polled_ok.load(Ordering::Relaxed) {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                error!(
+                    grace = %humantime::format_duration(grace),
+                    "No source completed a poll within the grace period, shutting down"
+                );
+                all_down.store(true, Ordering::Relaxed);
+                shutdown.store(true, Ordering::Relaxed);
+                break;
+            }
+            thread::sleep((deadline - now).min(Duration::from_millis(100)));
+        }
+    });
+}
+
+pub(crate) fn current_unix_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
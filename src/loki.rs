@@ -0,0 +1,154 @@
+//! Loki push payloads, an alternative to OTLP for the same batches.
+//!
+//! With `payload_format = "loki"` the OTLP client posts
+//! `{"streams": [{"stream": {labels}, "values": [[ts, line], ...]}]}` to
+//! `{otlp_endpoint}/loki/api/v1/push`. Fetching, filtering and cursor
+//! handling are unchanged.
+
+use crate::config::{BodyFormat, TimestampSource};
+use crate::journal::JournalEntry;
+use crate::otlp::PayloadOptions;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Loki HTTP push path.
+pub const DEFAULT_PUSH_PATH: &str = "/loki/api/v1/push";
+
+#[derive(Serialize)]
+pub(crate) struct PushRequest {
+    streams: Vec<Stream>,
+}
+
+#[derive(Serialize)]
+struct Stream {
+    stream: BTreeMap<String, String>,
+    values: Vec<[String; 2]>,
+}
+
+/// Loki label names must match `[a-zA-Z_][a-zA-Z0-9_]*`; anything else
+/// becomes `_`, so `os.version` is sent as `os_version`.
+fn label_name(key: &str) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Group `entries` into streams labelled with `host`, `unit` and `priority`,
+/// plus the forwarder's `resource_attributes` and the source `labels` (which
+/// win on a clash).
+pub(crate) fn build_push_request(
+    host_name: &str,
+    entries: &[JournalEntry],
+    labels: &HashMap<String, String>,
+    options: &PayloadOptions,
+) -> PushRequest {
+    let mut common = BTreeMap::from([("host".to_string(), host_name.to_string())]);
+    for (key, value) in options.resource_attributes.iter().chain(labels) {
+        common.insert(label_name(key), value.clone());
+    }
+
+    let mut streams: BTreeMap<BTreeMap<String, String>, Vec<[String; 2]>> = BTreeMap::new();
+    for entry in entries {
+        let mut stream = common.clone();
+        let unit = std::iter::once(&options.group_by)
+            .chain(&options.service_name_fallback)
+            .find_map(|field| entry.field(field).filter(|v| !v.is_empty()))
+            .unwrap_or("unknown");
+        stream
+            .entry("unit".to_string())
+            .or_insert_with(|| unit.to_string());
+        if let Some(priority) = entry.priority {
+            stream
+                .entry("priority".to_string())
+                .or_insert_with(|| priority.to_string());
+        }
+
+        let time_us = match options.timestamp_source {
+            TimestampSource::Source => entry.source_realtime_timestamp(),
+            TimestampSource::Journald => None,
+        }
+        .unwrap_or(entry.realtime_timestamp);
+        let line = match (options.body_format, &entry.raw) {
+            (BodyFormat::RawJson, Some(raw)) => raw.clone(),
+            _ => entry.message.clone(),
+        };
+        streams
+            .entry(stream)
+            .or_default()
+            .push([(time_us * 1000).to_string(), line]);
+    }
+
+    PushRequest {
+        streams: streams
+            .into_iter()
+            .map(|(stream, values)| Stream { stream, values })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(unit: Option<&str>, priority: Option<u8>, message: &str) -> JournalEntry {
+        JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            realtime_timestamp: 1_700_000_000_000_000,
+            message: message.to_string(),
+            priority,
+            systemd_unit: unit.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_build_push_request() {
+        let entries = vec![
+            entry(Some("nginx.service"), Some(6), "GET /"),
+            entry(Some("nginx.service"), Some(3), "upstream down"),
+            entry(Some("nginx.service"), Some(6), "GET /health"),
+            entry(None, None, "no unit"),
+        ];
+        let labels = HashMap::from([("os.version".to_string(), "41".to_string())]);
+        let json = serde_json::to_value(build_push_request(
+            "host-01",
+            &entries,
+            &labels,
+            &PayloadOptions::default(),
+        ))
+        .unwrap();
+
+        let streams = json["streams"].as_array().unwrap();
+        assert_eq!(streams.len(), 3);
+        let info = streams
+            .iter()
+            .find(|s| s["stream"]["unit"] == "nginx.service" && s["stream"]["priority"] == "6")
+            .unwrap();
+        assert_eq!(info["stream"]["host"], "host-01");
+        assert_eq!(info["stream"]["os_version"], "41");
+        assert_eq!(
+            info["values"],
+            serde_json::json!([
+                ["1700000000000000000", "GET /"],
+                ["1700000000000000000", "GET /health"]
+            ])
+        );
+        let unknown = streams
+            .iter()
+            .find(|s| s["stream"]["unit"] == "unknown")
+            .unwrap();
+        assert!(unknown["stream"].get("priority").is_none());
+    }
+
+    #[test]
+    fn test_label_name() {
+        assert_eq!(label_name("k8s.cluster.name"), "k8s_cluster_name");
+        assert_eq!(label_name("env"), "env");
+        assert_eq!(label_name("1zone"), "_1zone");
+    }
+}
@@ -3,34 +3,27 @@
 //! Pull-based journal log forwarder. Collects logs from remote
 //! systemd-journal-gatewayd endpoints and forwards them to an OTLP-compatible backend.
 
-mod collector;
-mod config;
-mod cursor;
-mod journal;
-mod metrics;
-mod otlp;
-
 use clap::Parser;
-use config::{Cli, Config};
+use otel_journal_gatewayd_forwarder::config::{Cli, Config, Sink};
+use otel_journal_gatewayd_forwarder::{RunOptions, benchmark, cursor};
 use std::process::ExitCode;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::thread;
-use std::time::Duration;
 use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 
-#[cfg(unix)]
-use sd_notify::NotifyState;
-
 fn main() -> ExitCode {
     let cli = Cli::parse();
 
+    // Load configuration before logging: the stdout sink moves logs to stderr
+    let config = Config::load(&cli.config);
+    let stdout_sink = cli.sink == Sink::Stdout || config.as_ref().is_ok_and(Config::stdout_sink);
+
     // Setup logging
-    setup_logging(&cli);
+    setup_logging(&cli, stdout_sink);
 
-    // Load configuration
-    let config = match Config::load(&cli.config) {
+    let config = match config {
         Ok(config) => config,
         Err(e) => {
             error!(error = %e, "Failed to load configuration");
@@ -38,14 +31,64 @@ fn main() -> ExitCode {
         }
     };
 
+    // --diff-config mode: compare resolved settings and exit
+    if let Some(other_path) = &cli.diff_config {
+        let other = match Config::load(other_path) {
+            Ok(other) => other,
+            Err(e) => {
+                error!(error = %e, path = %other_path.display(), "Failed to load configuration");
+                return ExitCode::from(1);
+            }
+        };
+        let changes = config.diff(&other);
+        if changes.is_empty() {
+            println!("No differences");
+        }
+        for change in &changes {
+            println!("{}", change);
+        }
+        return ExitCode::SUCCESS;
+    }
+
     // Validate configuration
     if let Err(e) = config.validate() {
         error!(error = %e, "Configuration validation failed");
         return ExitCode::from(1);
     }
 
+    let mut warnings = config.warnings();
+
+    // --validate also checks the cursor directory and self-tests its storage,
+    // but leaves a missing directory for startup to create
+    if cli.validate {
+        if !config.cursor_dir.try_exists().unwrap_or(true) {
+            warnings.push(format!(
+                "cursor_dir {} does not exist; it will be created on startup",
+                config.cursor_dir.display()
+            ));
+        } else if let Err(e) = cursor::check_writable(&config.cursor_dir) {
+            error!(error = %e, "Configuration validation failed");
+            return ExitCode::from(1);
+        } else {
+            warnings.extend(cursor::storage_warnings(&config.cursor_dir));
+        }
+    }
+
+    for warning in &warnings {
+        warn!("Configuration warning: {}", warning);
+    }
+
     // --validate mode: exit after validation
     if cli.validate {
+        if cli.strict && !warnings.is_empty() {
+            println!("Configuration has {} warning(s):", warnings.len());
+            for warning in &warnings {
+                println!("  - {}", warning);
+            }
+            error!("Configuration warnings treated as errors (--strict)");
+            return ExitCode::from(1);
+        }
+
         info!("Configuration is valid");
         println!("Configuration validated successfully:");
         println!("  OTLP endpoint: {}", config.otlp_endpoint);
@@ -56,9 +99,20 @@ fn main() -> ExitCode {
         for source in &config.sources {
             println!("    - {} ({})", source.name, source.url);
         }
+        if !warnings.is_empty() {
+            println!("  Warnings: {}", warnings.len());
+            for warning in &warnings {
+                println!("    - {}", warning);
+            }
+        }
         return ExitCode::SUCCESS;
     }
 
+    // --benchmark mode: push synthetic batches, report, and exit
+    if let Some(duration) = cli.benchmark {
+        return run_benchmark(&config, &cli, duration);
+    }
+
     // Run the forwarder
     if let Err(e) = run(config, &cli) {
         error!(error = %e, "Fatal error");
@@ -68,7 +122,7 @@ fn main() -> ExitCode {
     ExitCode::SUCCESS
 }
 
-fn setup_logging(cli: &Cli) {
+fn setup_logging(cli: &Cli, stderr: bool) {
     let filter = if cli.quiet {
         "error"
     } else {
@@ -82,179 +136,111 @@ fn setup_logging(cli: &Cli) {
 
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter));
 
-    tracing_subscriber::fmt()
+    let builder = tracing_subscriber::fmt()
         .with_env_filter(filter)
-        .with_target(false)
-        .init();
+        .with_target(false);
+    // Keep stdout clean for the OTLP JSON stream
+    if stderr {
+        builder.with_writer(std::io::stderr).init();
+    } else {
+        builder.init();
+    }
 }
 
-fn run(config: Config, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
-    info!(
-        otlp_endpoint = %config.otlp_endpoint,
-        sources = config.sources.len(),
-        "Starting forwarder"
-    );
-
-    // Shared shutdown flag
+fn run_benchmark(config: &Config, cli: &Cli, duration: std::time::Duration) -> ExitCode {
     let shutdown = Arc::new(AtomicBool::new(false));
-
-    // Setup signal handlers
-    setup_signals(shutdown.clone())?;
-
-    // Setup metrics if enabled
-    let metrics = if let Some(ref addr) = cli.metrics {
-        let state = Arc::new(metrics::MetricsState::new());
-        metrics::start_server(addr, state.clone())?;
-        Some(state)
-    } else {
-        None
-    };
-
-    // Create shared OTLP client
-    let otlp = Arc::new(otlp::OtlpClient::new(
-        &config.otlp_endpoint,
-        config.tls.as_ref(),
-        &config.otlp_headers,
-    )?);
-
-    // Start collector threads, each with a freshness tick
-    let mut source_states = Vec::new();
-
-    for source in config.sources {
-        let cursor = cursor::CursorManager::new(&config.cursor_dir, &source.name)?;
-        let collector = collector::Collector::new(
-            source,
-            &config.tls,
-            otlp.clone(),
-            cursor,
-            config.batch_size,
-            config.max_field_bytes,
-            metrics.clone(),
-        )?;
-
-        let shutdown = shutdown.clone();
-        let poll_interval = config.poll_interval;
-        let once = cli.once;
-        let tick = Arc::new(AtomicU64::new(current_unix_ms()));
-        let thread_tick = tick.clone();
-        let source_name = collector.source_name().to_string();
-
-        let handle = thread::spawn(move || {
-            collector::run_loop(collector, poll_interval, shutdown, once, thread_tick);
-        });
-
-        source_states.push((source_name, poll_interval, tick, handle));
+    let unused = || Arc::new(AtomicU64::new(0));
+    if let Err(e) = setup_signals(shutdown.clone(), unused(), unused()) {
+        error!(error = %e, "Failed to set up signal handlers");
+        return ExitCode::from(1);
     }
 
-    // Notify systemd that the service is ready now that all collectors are spawned.
-    #[cfg(unix)]
-    {
-        if let Err(e) = sd_notify::notify(false, &[NotifyState::Ready]) {
-            warn!(error = %e, "Failed to send systemd ready notification");
-        } else {
-            info!("Sent systemd ready notification");
+    let options = benchmark::BenchmarkOptions {
+        duration,
+        rate: cli.benchmark_rate,
+        batch_size: config.batch_size,
+    };
+    match benchmark::run(config, &options, &shutdown) {
+        Ok(report) => {
+            println!("{}", report);
+            ExitCode::SUCCESS
         }
-    }
-
-    // Wait for all collectors to finish, optionally pinging the systemd watchdog.
-    #[cfg(unix)]
-    {
-        let mut usec = 0u64;
-        let enabled = sd_notify::watchdog_enabled(false, &mut usec);
-        if enabled {
-            let timeout = Duration::from_micros(usec);
-            info!(timeout_ms = timeout.as_millis(), "systemd watchdog enabled");
-            wait_with_watchdog(source_states, shutdown.clone(), timeout)?;
-        } else {
-            info!("systemd watchdog not enabled; joining collectors directly");
-            join_collectors(source_states)?;
+        Err(e) => {
+            error!(error = %e, "Benchmark failed");
+            ExitCode::from(1)
         }
     }
+}
 
-    #[cfg(not(unix))]
-    {
-        join_collectors(source_states)?;
-    }
+fn run(config: Config, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    // Shared shutdown flag
+    let shutdown = Arc::new(AtomicBool::new(false));
 
-    info!("All collectors stopped, exiting");
-    Ok(())
+    // Bumped on SIGUSR1 to request a cursor flush and status line
+    let status_requests = Arc::new(AtomicU64::new(0));
+    // Bumped on SIGHUP to reload the configuration file
+    let reload_requests = Arc::new(AtomicU64::new(0));
+
+    // Setup signal handlers
+    setup_signals(
+        shutdown.clone(),
+        status_requests.clone(),
+        reload_requests.clone(),
+    )?;
+
+    otel_journal_gatewayd_forwarder::run_with(
+        config,
+        shutdown,
+        RunOptions {
+            once: cli.once,
+            max_runtime: cli.max_runtime,
+            metrics_addr: cli.metrics.clone(),
+            debug_dump: cli.debug_dump.clone(),
+            pretty_payload: cli.pretty_payload,
+            status_requests,
+            cursor_self_test: cli.cursor_self_test,
+            enricher: None,
+            stdout_sink: cli.sink == Sink::Stdout,
+            request_signer: None,
+            allow_shared_cursor_dir: cli.allow_shared_cursor_dir,
+            config_path: Some(cli.config.clone()),
+            reload_requests,
+            systemd_notify: true,
+        },
+    )
 }
 
-/// Wait for collectors to finish while periodically pinging the systemd
-/// watchdog as long as every source has ticked within its own freshness window.
 #[cfg(unix)]
-fn wait_with_watchdog(
-    source_states: Vec<(String, Duration, Arc<AtomicU64>, thread::JoinHandle<()>)>,
+fn setup_signals(
     shutdown: Arc<AtomicBool>,
-    timeout: Duration,
+    status_requests: Arc<AtomicU64>,
+    reload_requests: Arc<AtomicU64>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let ping_interval = timeout / 2;
-    let now = current_unix_ms;
-
-    loop {
-        // Ping only while every source still appears alive.
-        let all_fresh = source_states.iter().all(|(_, poll_interval, tick, _)| {
-            let window = poll_interval.saturating_mul(5).max(Duration::from_secs(60));
-            now().saturating_sub(tick.load(Ordering::Relaxed)) <= window.as_millis() as u64
-        });
-
-        if all_fresh {
-            if let Err(e) = sd_notify::notify(false, &[NotifyState::Watchdog]) {
-                warn!(error = %e, "Failed to send systemd watchdog notification");
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM, SIGUSR1};
+
+    signal_hook::flag::register(SIGTERM, shutdown.clone())?;
+    signal_hook::flag::register(SIGINT, shutdown.clone())?;
+
+    let mut signals = signal_hook::iterator::Signals::new([SIGUSR1, SIGHUP])?;
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            if signal == SIGHUP {
+                info!("SIGHUP received, reloading configuration");
+                reload_requests.fetch_add(1, Ordering::Relaxed);
+            } else {
+                info!("SIGUSR1 received, flushing cursors and logging status");
+                status_requests.fetch_add(1, Ordering::Relaxed);
             }
-        } else {
-            warn!("Skipping systemd watchdog ping: one or more sources appear stale");
-        }
-
-        // Sleep in short slices so we can detect finished threads promptly.
-        let mut remaining = ping_interval;
-        while remaining > Duration::ZERO && !shutdown.load(Ordering::Relaxed) {
-            let slice = remaining.min(Duration::from_millis(100));
-            thread::sleep(slice);
-            remaining = remaining.saturating_sub(slice);
-
-            // If any thread finished (e.g. --once), switch to a plain join.
-            if source_states
-                .iter()
-                .any(|(_, _, _, handle)| handle.is_finished())
-            {
-                return join_collectors(source_states);
-            }
-        }
-
-        if shutdown.load(Ordering::Relaxed) {
-            return join_collectors(source_states);
-        }
-    }
-}
-
-/// Join all collector threads and surface any panics.
-fn join_collectors(
-    source_states: Vec<(String, Duration, Arc<AtomicU64>, thread::JoinHandle<()>)>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    for (source_name, _, _, handle) in source_states {
-        if let Err(e) = handle.join() {
-            warn!(source = %source_name, panic = ?e, "Collector thread panicked");
         }
-    }
-    Ok(())
-}
-
-fn current_unix_ms() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64
-}
-
-#[cfg(unix)]
-fn setup_signals(shutdown: Arc<AtomicBool>) -> Result<(), Box<dyn std::error::Error>> {
-    signal_hook::flag::register(signal_hook::consts::SIGTERM, shutdown.clone())?;
-    signal_hook::flag::register(signal_hook::consts::SIGINT, shutdown.clone())?;
+    });
     Ok(())
 }
 
 #[cfg(not(unix))]
-fn setup_signals(_shutdown: Arc<AtomicBool>) -> Result<(), Box<dyn std::error::Error>> {
+fn setup_signals(
+    _shutdown: Arc<AtomicBool>,
+    _status_requests: Arc<AtomicU64>,
+    _reload_requests: Arc<AtomicU64>,
+) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
@@ -8,7 +8,7 @@ use std::io::{Read, Write};
 use std::net::TcpListener;
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tracing::{debug, info, warn};
 
@@ -27,12 +27,31 @@ pub struct SourceMetrics {
     pub last_poll_duration: Option<Duration>,
     pub last_entry_realtime_us: Option<u64>,
     pub last_success_timestamp: Option<f64>,
+    pub unexpected_empty: u64,
+    pub resume_skipped: u64,
+    pub sampled_out: HashMap<String, u64>,
+    pub effective_poll_interval: Option<Duration>,
+    pub dropped: HashMap<String, u64>,
+    pub last_forward_timestamp: Option<f64>,
+    pub journal_retries: u64,
+    pub otlp_retries: u64,
+    pub stream_reconnects: u64,
+    pub journal_in_flight: usize,
+    pub last_cursor_advance: Option<Instant>,
+    pub last_serialize_duration: Option<Duration>,
+    pub invalid_priority: u64,
+    pub batch_fill_sum: f64,
+    pub batch_fill_count: u64,
+    pub gatewayd_version: Option<String>,
+    pub unacked_entries: usize,
 }
 
 /// Shared metrics state
 #[derive(Debug, Default)]
 pub struct MetricsState {
     sources: RwLock<HashMap<String, SourceMetrics>>,
+    /// Hash of the resolved config currently in effect.
+    config_hash: RwLock<Option<String>>,
 }
 
 impl MetricsState {
@@ -40,11 +59,24 @@ impl MetricsState {
         Self::default()
     }
 
+    /// Set the config hash reported by `ojgf_config_hash_info`
+    pub fn set_config_hash(&self, hash: impl Into<String>) {
+        *self.config_hash.write() = Some(hash.into());
+    }
+
     /// Record forwarded entries
     pub fn record_forwarded(&self, source: &str, count: u64) {
         let mut sources = self.sources.write();
         let metrics = sources.entry(source.to_string()).or_default();
         metrics.entries_forwarded += count;
+        if count > 0 {
+            metrics.last_forward_timestamp = Some(
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs_f64(),
+            );
+        }
     }
 
     /// Record a poll error
@@ -84,11 +116,129 @@ impl MetricsState {
         );
     }
 
+    /// Record entries held for a source whose cursor is not yet persisted
+    pub fn record_unacked(&self, source: &str, count: usize) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.unacked_entries = count;
+    }
+
+    /// Record the gatewayd `Server` header last seen for a source
+    pub fn set_gatewayd_version(&self, source: &str, version: &str) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.gatewayd_version = Some(version.to_string());
+    }
+
+    /// Record an empty fetch that immediately followed a full batch mid-drain
+    pub fn record_unexpected_empty(&self, source: &str) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.unexpected_empty += 1;
+    }
+
+    /// Record a startup that skipped the backlog under `max_resume_age`
+    pub fn record_resume_skipped(&self, source: &str) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.resume_skipped += 1;
+    }
+
+    /// Record entries whose PRIORITY was outside 0-7
+    pub fn record_invalid_priority(&self, source: &str, count: u64) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.invalid_priority += count;
+    }
+
+    /// Record entries intentionally dropped by sampling
+    pub fn record_sampled_out(&self, source: &str, severity: &str, count: u64) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        *metrics.sampled_out.entry(severity.to_string()).or_default() += count;
+    }
+
+    /// Record the sleep currently applied between poll cycles (after backoff)
+    pub fn record_effective_interval(&self, source: &str, interval: Duration) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.effective_poll_interval = Some(interval);
+    }
+
+    /// Record entries dropped by a filter before forwarding
+    pub fn record_dropped(&self, source: &str, reason: &str, count: u64) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        *metrics.dropped.entry(reason.to_string()).or_default() += count;
+    }
+
+    /// Record journal fetch retries
+    pub fn record_journal_retries(&self, source: &str, count: u64) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.journal_retries += count;
+    }
+
+    /// Record one in-cycle OTLP send retry
+    pub fn record_otlp_retry(&self, source: &str) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.otlp_retries += 1;
+    }
+
+    /// Record a follow stream that dropped and is being reopened
+    pub fn record_stream_reconnect(&self, source: &str) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.stream_reconnects += 1;
+    }
+
+    /// Record that the source's durable cursor moved forward
+    pub fn record_cursor_advance(&self, source: &str) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.last_cursor_advance = Some(Instant::now());
+    }
+
+    /// Record how long the last OTLP payload took to serialize
+    pub fn record_serialize_duration(&self, source: &str, duration: Duration) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.last_serialize_duration = Some(duration);
+    }
+
+    /// Record how full a fetch was: entries returned over entries requested
+    pub fn record_batch_fill(&self, source: &str, ratio: f64) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.batch_fill_sum += ratio;
+        metrics.batch_fill_count += 1;
+    }
+
+    /// Record the number of in-flight gatewayd requests
+    pub fn record_journal_in_flight(&self, source: &str, count: usize) {
+        let mut sources = self.sources.write();
+        let metrics = sources.entry(source.to_string()).or_default();
+        metrics.journal_in_flight = count;
+    }
+
     /// Generate Prometheus metrics output
     pub fn render(&self) -> String {
         let sources = self.sources.read();
         let mut output = String::new();
 
+        // Resolved config identity, for verifying rollouts
+        if let Some(hash) = self.config_hash.read().as_deref() {
+            output.push_str(
+                "# HELP ojgf_config_hash_info Hash of the resolved configuration in effect\n",
+            );
+            output.push_str("# TYPE ojgf_config_hash_info gauge\n");
+            output.push_str(&format!(
+                "ojgf_config_hash_info{{hash=\"{}\"}} 1\n",
+                escape_label(hash)
+            ));
+        }
+
         // Entries forwarded
         output.push_str("# HELP ojgf_entries_forwarded_total Total journal entries forwarded\n");
         output.push_str("# TYPE ojgf_entries_forwarded_total counter\n");
@@ -114,6 +264,86 @@ impl MetricsState {
             }
         }
 
+        // Journal fetch retries
+        output.push_str(
+            "# HELP ojgf_journal_retries_total Journal fetch retries after transient failures\n",
+        );
+        output.push_str("# TYPE ojgf_journal_retries_total counter\n");
+        for (source, metrics) in sources.iter() {
+            output.push_str(&format!(
+                "ojgf_journal_retries_total{{source=\"{}\"}} {}\n",
+                escape_label(source),
+                metrics.journal_retries
+            ));
+        }
+
+        // OTLP send retries
+        output.push_str(
+            "# HELP ojgf_otlp_retries_total OTLP send retries after transient failures\n",
+        );
+        output.push_str("# TYPE ojgf_otlp_retries_total counter\n");
+        for (source, metrics) in sources.iter() {
+            output.push_str(&format!(
+                "ojgf_otlp_retries_total{{source=\"{}\"}} {}\n",
+                escape_label(source),
+                metrics.otlp_retries
+            ));
+        }
+
+        // Follow streams reopened after dropping
+        output.push_str(
+            "# HELP ojgf_stream_reconnects_total Follow streams reopened after the connection dropped\n",
+        );
+        output.push_str("# TYPE ojgf_stream_reconnects_total counter\n");
+        for (source, metrics) in sources.iter() {
+            output.push_str(&format!(
+                "ojgf_stream_reconnects_total{{source=\"{}\"}} {}\n",
+                escape_label(source),
+                metrics.stream_reconnects
+            ));
+        }
+
+        // Entries held without a persisted cursor
+        output.push_str(
+            "# HELP ojgf_unacked_entries Fetched entries held for batching whose cursor is not yet persisted\n",
+        );
+        output.push_str("# TYPE ojgf_unacked_entries gauge\n");
+        for (source, metrics) in sources.iter() {
+            output.push_str(&format!(
+                "ojgf_unacked_entries{{source=\"{}\"}} {}\n",
+                escape_label(source),
+                metrics.unacked_entries
+            ));
+        }
+
+        // gatewayd software version
+        output.push_str(
+            "# HELP ojgf_gatewayd_version_info Server header reported by the source gatewayd\n",
+        );
+        output.push_str("# TYPE ojgf_gatewayd_version_info gauge\n");
+        for (source, metrics) in sources.iter() {
+            if let Some(version) = &metrics.gatewayd_version {
+                output.push_str(&format!(
+                    "ojgf_gatewayd_version_info{{source=\"{}\",version=\"{}\"}} 1\n",
+                    escape_label(source),
+                    escape_label(version)
+                ));
+            }
+        }
+
+        // In-flight gatewayd requests
+        output.push_str(
+            "# HELP ojgf_journal_in_flight_requests In-flight requests to the source gatewayd\n",
+        );
+        output.push_str("# TYPE ojgf_journal_in_flight_requests gauge\n");
+        for (source, metrics) in sources.iter() {
+            output.push_str(&format!(
+                "ojgf_journal_in_flight_requests{{source=\"{}\"}} {}\n",
+                escape_label(source),
+                metrics.journal_in_flight
+            ));
+        }
+
         // Last poll timestamp
         output.push_str(
             "# HELP ojgf_last_poll_timestamp_seconds Timestamp of last successful poll\n",
@@ -129,6 +359,21 @@ impl MetricsState {
             }
         }
 
+        // Last forward of a non-empty batch
+        output.push_str(
+            "# HELP ojgf_last_forward_timestamp_seconds Timestamp of last forward of one or more entries\n",
+        );
+        output.push_str("# TYPE ojgf_last_forward_timestamp_seconds gauge\n");
+        for (source, metrics) in sources.iter() {
+            if let Some(ts) = metrics.last_forward_timestamp {
+                output.push_str(&format!(
+                    "ojgf_last_forward_timestamp_seconds{{source=\"{}\"}} {:.3}\n",
+                    escape_label(source),
+                    ts
+                ));
+            }
+        }
+
         // Poll duration
         output.push_str("# HELP ojgf_poll_duration_seconds Duration of last poll cycle\n");
         output.push_str("# TYPE ojgf_poll_duration_seconds gauge\n");
@@ -142,6 +387,39 @@ impl MetricsState {
             }
         }
 
+        // Payload serialization time
+        output.push_str(
+            "# HELP ojgf_serialize_duration_seconds Time to serialize the last OTLP payload\n",
+        );
+        output.push_str("# TYPE ojgf_serialize_duration_seconds gauge\n");
+        for (source, metrics) in sources.iter() {
+            if let Some(duration) = metrics.last_serialize_duration {
+                output.push_str(&format!(
+                    "ojgf_serialize_duration_seconds{{source=\"{}\"}} {:.6}\n",
+                    escape_label(source),
+                    duration.as_secs_f64()
+                ));
+            }
+        }
+
+        // Fetch fill ratio; rate(_sum) / rate(_count) is the average
+        output.push_str(
+            "# HELP ojgf_batch_fill_ratio Entries fetched per poll relative to the requested batch size\n",
+        );
+        output.push_str("# TYPE ojgf_batch_fill_ratio summary\n");
+        for (source, metrics) in sources.iter() {
+            output.push_str(&format!(
+                "ojgf_batch_fill_ratio_sum{{source=\"{}\"}} {:.6}\n",
+                escape_label(source),
+                metrics.batch_fill_sum
+            ));
+            output.push_str(&format!(
+                "ojgf_batch_fill_ratio_count{{source=\"{}\"}} {}\n",
+                escape_label(source),
+                metrics.batch_fill_count
+            ));
+        }
+
         // Source lag (now - last forwarded entry realtime)
         output.push_str(
             "# HELP ojgf_source_lag_seconds Time since the last forwarded entry was emitted\n",
@@ -177,6 +455,107 @@ impl MetricsState {
             }
         }
 
+        // Forward progress of the durable checkpoint
+        output.push_str(
+            "# HELP ojgf_seconds_since_cursor_advance Seconds since the cursor last moved forward (or since startup)\n",
+        );
+        output.push_str("# TYPE ojgf_seconds_since_cursor_advance gauge\n");
+        for (source, metrics) in sources.iter() {
+            if let Some(at) = metrics.last_cursor_advance {
+                output.push_str(&format!(
+                    "ojgf_seconds_since_cursor_advance{{source=\"{}\"}} {:.3}\n",
+                    escape_label(source),
+                    at.elapsed().as_secs_f64()
+                ));
+            }
+        }
+
+        // Empty fetches right after a full batch
+        output.push_str(
+            "# HELP ojgf_unexpected_empty_total Empty fetches that immediately followed a full batch during drain\n",
+        );
+        output.push_str("# TYPE ojgf_unexpected_empty_total counter\n");
+        for (source, metrics) in sources.iter() {
+            output.push_str(&format!(
+                "ojgf_unexpected_empty_total{{source=\"{}\"}} {}\n",
+                escape_label(source),
+                metrics.unexpected_empty
+            ));
+        }
+
+        // Backlogs skipped because the saved cursor was too old
+        output.push_str(
+            "# HELP ojgf_resume_skipped_total Startups that skipped the backlog because the saved cursor exceeded max_resume_age\n",
+        );
+        output.push_str("# TYPE ojgf_resume_skipped_total counter\n");
+        for (source, metrics) in sources.iter() {
+            output.push_str(&format!(
+                "ojgf_resume_skipped_total{{source=\"{}\"}} {}\n",
+                escape_label(source),
+                metrics.resume_skipped
+            ));
+        }
+
+        // Entries with a PRIORITY outside 0-7
+        output.push_str(
+            "# HELP ojgf_invalid_priority_total Journal entries with a PRIORITY outside 0-7\n",
+        );
+        output.push_str("# TYPE ojgf_invalid_priority_total counter\n");
+        for (source, metrics) in sources.iter() {
+            output.push_str(&format!(
+                "ojgf_invalid_priority_total{{source=\"{}\"}} {}\n",
+                escape_label(source),
+                metrics.invalid_priority
+            ));
+        }
+
+        // Entries dropped by sampling
+        output.push_str(
+            "# HELP ojgf_entries_sampled_out_total Journal entries dropped by severity sampling\n",
+        );
+        output.push_str("# TYPE ojgf_entries_sampled_out_total counter\n");
+        for (source, metrics) in sources.iter() {
+            for (severity, count) in &metrics.sampled_out {
+                output.push_str(&format!(
+                    "ojgf_entries_sampled_out_total{{source=\"{}\",severity=\"{}\"}} {}\n",
+                    escape_label(source),
+                    escape_label(severity),
+                    count
+                ));
+            }
+        }
+
+        // Effective poll interval
+        output.push_str(
+            "# HELP ojgf_effective_poll_interval_seconds Current sleep between poll cycles after backoff or idle stretching\n",
+        );
+        output.push_str("# TYPE ojgf_effective_poll_interval_seconds gauge\n");
+        for (source, metrics) in sources.iter() {
+            if let Some(interval) = metrics.effective_poll_interval {
+                output.push_str(&format!(
+                    "ojgf_effective_poll_interval_seconds{{source=\"{}\"}} {:.3}\n",
+                    escape_label(source),
+                    interval.as_secs_f64()
+                ));
+            }
+        }
+
+        // Entries dropped by filters
+        output.push_str(
+            "# HELP ojgf_entries_dropped_total Journal entries dropped by filters before forwarding\n",
+        );
+        output.push_str("# TYPE ojgf_entries_dropped_total counter\n");
+        for (source, metrics) in sources.iter() {
+            for (reason, count) in &metrics.dropped {
+                output.push_str(&format!(
+                    "ojgf_entries_dropped_total{{source=\"{}\",reason=\"{}\"}} {}\n",
+                    escape_label(source),
+                    escape_label(reason),
+                    count
+                ));
+            }
+        }
+
         output
     }
 }
@@ -214,47 +593,112 @@ pub fn start_server(addr: &str, state: Arc<MetricsState>) -> Result<(), MetricsE
     Ok(())
 }
 
-fn handle_request(mut stream: impl Read + Write, state: &MetricsState) -> std::io::Result<()> {
-    let mut buf = [0u8; 1024];
-    let _ = stream.read(&mut buf)?;
-
-    // Simple HTTP parsing
-    let request = String::from_utf8_lossy(&buf);
-    let is_health_request = request.starts_with("GET /healthz");
-    let is_metrics_request = request.starts_with("GET /metrics") || request.starts_with("GET / ");
-
-    if is_health_request {
-        let body = "ok";
-        let response = format!(
-            "HTTP/1.1 200 OK\r\n\
-             Content-Type: text/plain\r\n\
-             Content-Length: {}\r\n\
-             Connection: close\r\n\
-             \r\n\
-             {}",
-            body.len(),
-            body
-        );
-        stream.write_all(response.as_bytes())?;
-    } else if is_metrics_request {
-        let body = state.render();
-        let response = format!(
-            "HTTP/1.1 200 OK\r\n\
-             Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
-             Content-Length: {}\r\n\
-             Connection: close\r\n\
-             \r\n\
-             {}",
-            body.len(),
-            body
+/// Response produced by a route handler.
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+    allow: Option<&'static str>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: String) -> Self {
+        Self {
+            status: "200 OK",
+            content_type,
+            body,
+            allow: None,
+        }
+    }
+
+    fn error(status: &'static str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            body: String::new(),
+            allow: None,
+        }
+    }
+
+    fn write_to(&self, stream: &mut impl Write) -> std::io::Result<()> {
+        let mut head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
         );
-        stream.write_all(response.as_bytes())?;
-    } else {
-        let response = "HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\n";
-        stream.write_all(response.as_bytes())?;
+        if let Some(allow) = self.allow {
+            head.push_str(&format!("Allow: {}\r\n", allow));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(self.body.as_bytes())
     }
+}
 
-    Ok(())
+type Handler = fn(&MetricsState) -> Response;
+
+/// Exact-path routes as (method, path, handler).
+const ROUTES: &[(&str, &str, Handler)] = &[
+    ("GET", "/", metrics_handler),
+    ("GET", "/metrics", metrics_handler),
+    ("GET", "/healthz", health_handler),
+];
+
+fn metrics_handler(state: &MetricsState) -> Response {
+    Response::ok("text/plain; version=0.0.4; charset=utf-8", state.render())
+}
+
+fn health_handler(_state: &MetricsState) -> Response {
+    Response::ok("text/plain", "ok".to_string())
+}
+
+/// Split an HTTP/1.x request line into method and path (query string dropped).
+fn parse_request_line(request: &str) -> Option<(&str, &str)> {
+    let line = request.lines().next()?;
+    let mut parts = line.split(' ');
+    let method = parts.next().filter(|m| !m.is_empty())?;
+    let target = parts.next()?;
+    let version = parts.next()?;
+    if parts.next().is_some() || !version.starts_with("HTTP/1.") || !target.starts_with('/') {
+        return None;
+    }
+    let path = target.split(['?', '#']).next().unwrap_or(target);
+    Some((method, path))
+}
+
+/// Dispatch to the matching route: 404 for unknown paths, 405 for a known
+/// path with the wrong method.
+fn route(method: &str, path: &str, state: &MetricsState) -> Response {
+    let mut allowed = None;
+    for (route_method, route_path, handler) in ROUTES {
+        if *route_path != path {
+            continue;
+        }
+        if *route_method == method {
+            return handler(state);
+        }
+        allowed = Some(*route_method);
+    }
+    match allowed {
+        Some(allow) => Response {
+            allow: Some(allow),
+            ..Response::error("405 Method Not Allowed")
+        },
+        None => Response::error("404 Not Found"),
+    }
+}
+
+fn handle_request(mut stream: impl Read + Write, state: &MetricsState) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf)?;
+
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let response = match parse_request_line(&request) {
+        Some((method, path)) => route(method, path, state),
+        None => Response::error("400 Bad Request"),
+    };
+    response.write_to(&mut stream)
 }
 
 #[cfg(test)]
@@ -268,12 +712,60 @@ mod tests {
         state.record_error("host-01", "timeout");
         state.record_poll("host-01", Duration::from_millis(234));
         state.record_last_entry("host-01", Some(1_703_456_789_000_000));
+        state.record_unexpected_empty("host-01");
+        state.record_resume_skipped("host-01");
+        state.record_sampled_out("host-01", "info", 7);
+        state.record_invalid_priority("host-01", 4);
+        state.record_effective_interval("host-01", Duration::from_secs(20));
+        state.record_dropped("host-01", "missing_field", 3);
+        state.record_journal_retries("host-01", 2);
+        state.record_otlp_retry("host-01");
+        state.record_stream_reconnect("host-01");
+        state.set_gatewayd_version("host-01", "systemd-journal-gatewayd/255");
+        state.record_unacked("host-01", 42);
+        state.record_journal_in_flight("host-01", 1);
+        state.record_cursor_advance("host-01");
+        state.record_serialize_duration("host-01", Duration::from_micros(1500));
+        state.set_config_hash("0123456789abcdef");
+        state.record_batch_fill("host-01", 1.0);
+        state.record_batch_fill("host-01", 0.25);
 
         let output = state.render();
         assert!(output.contains("ojgf_entries_forwarded_total{source=\"host-01\"} 100"));
         assert!(output.contains("ojgf_poll_errors_total{source=\"host-01\",error=\"timeout\"} 1"));
+        assert!(output.contains("ojgf_journal_retries_total{source=\"host-01\"} 2"));
+        assert!(output.contains("ojgf_otlp_retries_total{source=\"host-01\"} 1"));
+        assert!(output.contains("ojgf_stream_reconnects_total{source=\"host-01\"} 1"));
+        assert!(output.contains("ojgf_unacked_entries{source=\"host-01\"} 42"));
+        assert!(output.contains(
+            "ojgf_gatewayd_version_info{source=\"host-01\",version=\"systemd-journal-gatewayd/255\"} 1"
+        ));
+        assert!(output.contains("ojgf_journal_in_flight_requests{source=\"host-01\"} 1"));
+        assert!(output.contains("ojgf_seconds_since_cursor_advance{source=\"host-01\"} 0."));
+        assert!(output.contains("ojgf_serialize_duration_seconds{source=\"host-01\"} 0.001500"));
         assert!(output.contains("ojgf_source_lag_seconds{source=\"host-01\"}"));
         assert!(output.contains("ojgf_last_success_timestamp_seconds{source=\"host-01\"}"));
+        assert!(output.contains("ojgf_unexpected_empty_total{source=\"host-01\"} 1"));
+        assert!(output.contains("ojgf_resume_skipped_total{source=\"host-01\"} 1"));
+        assert!(output.contains("ojgf_batch_fill_ratio_sum{source=\"host-01\"} 1.250000"));
+        assert!(output.contains("ojgf_batch_fill_ratio_count{source=\"host-01\"} 2"));
+        assert!(output.contains("ojgf_config_hash_info{hash=\"0123456789abcdef\"} 1"));
+        assert!(output.contains("ojgf_invalid_priority_total{source=\"host-01\"} 4"));
+        assert!(output.contains("ojgf_last_forward_timestamp_seconds{source=\"host-01\"}"));
+        assert!(
+            output
+                .contains("ojgf_entries_sampled_out_total{source=\"host-01\",severity=\"info\"} 7")
+        );
+    }
+
+    #[test]
+    fn test_last_forward_only_on_nonempty() {
+        let state = MetricsState::new();
+        state.record_forwarded("idle", 0);
+        state.record_poll("idle", Duration::from_millis(5));
+        let output = state.render();
+        assert!(output.contains("ojgf_last_poll_timestamp_seconds{source=\"idle\"}"));
+        assert!(!output.contains("ojgf_last_forward_timestamp_seconds{source=\"idle\"}"));
     }
 
     #[test]
@@ -305,6 +797,42 @@ mod tests {
         assert!(response.contains("ojgf_entries_forwarded_total{source=\"host-01\"} 42"));
     }
 
+    fn request(raw: &[u8]) -> String {
+        let state = MetricsState::new();
+        let mut stream = MockStream {
+            read_buf: raw.to_vec(),
+            write_buf: Vec::new(),
+        };
+        handle_request(&mut stream, &state).unwrap();
+        String::from_utf8(stream.write_buf).unwrap()
+    }
+
+    #[test]
+    fn test_router_query_string_and_errors() {
+        assert!(request(b"GET /metrics?foo=bar HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 200 OK"));
+        assert!(request(b"GET /healthz HTTP/1.0\r\n\r\n").starts_with("HTTP/1.1 200 OK"));
+
+        let response = request(b"POST /metrics HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed"));
+        assert!(response.contains("Allow: GET\r\n"));
+
+        assert!(request(b"GET /metricsfoo HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404"));
+        assert!(request(b"GET /healthz/extra HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404"));
+        assert!(request(b"garbage\r\n\r\n").starts_with("HTTP/1.1 400"));
+        assert!(request(b"").starts_with("HTTP/1.1 400"));
+    }
+
+    #[test]
+    fn test_parse_request_line() {
+        assert_eq!(
+            parse_request_line("GET /a?b=c HTTP/1.1\r\nHost: x\r\n"),
+            Some(("GET", "/a"))
+        );
+        assert_eq!(parse_request_line("GET /a"), None);
+        assert_eq!(parse_request_line("GET http://x/a HTTP/1.1"), None);
+        assert_eq!(parse_request_line("GET /a HTTP/2 extra"), None);
+    }
+
     #[test]
     fn test_escape_label() {
         assert_eq!(escape_label("simple"), "simple");
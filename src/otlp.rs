@@ -1,19 +1,57 @@
 //! OTLP/HTTP client for forwarding logs.
 //!
 //! Sends logs to OTLP-compatible backends via HTTP/JSON.
-//! Endpoint: `{otlp_endpoint}/v1/logs`
+//! Endpoint: `{otlp_endpoint}/v1/logs` (or `{otlp_endpoint}/{otlp_path}`)
+//! With `otlp_protocol = "grpc"` the same payload goes out over gRPC instead.
 
+mod grpc;
+
+use crate::config::{
+    AttributeType, BinaryMessage, BodyFormat, CursorRange, DEFAULT_GROUP_BY, DEFAULT_OS_TYPE,
+    DEFAULT_SERVICE_NAME_FALLBACK, OtlpCompression, OtlpProtocol, PayloadFormat, SeverityLevel,
+    TimestampSource,
+};
 use crate::journal::JournalEntry;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use parking_lot::Mutex;
 use reqwest::StatusCode;
 use reqwest::blocking::Client;
 use serde::Serialize;
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 use tracing::{debug, trace, warn};
 
-/// HTTP timeout for OTLP requests
-const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default limit on a whole OTLP request, including the backend's processing
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default limit on establishing the OTLP connection
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connection and request time limits for the OTLP exporter.
+#[derive(Debug, Clone, Copy)]
+pub struct OtlpTimeouts {
+    /// Time allowed to establish a connection (TCP and TLS), so a dead
+    /// endpoint fails fast.
+    pub connect: Duration,
+    /// Time allowed for the whole request, so a slow but live backend can
+    /// finish processing a large batch.
+    pub request: Duration,
+}
+
+impl Default for OtlpTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: DEFAULT_CONNECT_TIMEOUT,
+            request: DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum OtlpError {
@@ -21,55 +59,564 @@ pub enum OtlpError {
     Http(#[from] reqwest::Error),
     #[error("Server rejected request: {status} - {body}")]
     ServerError { status: StatusCode, body: String },
+    #[error("Server throttled request; retry after {retry_after:?}")]
+    Throttled { retry_after: Duration },
     #[error("Configuration error: {0}")]
     Config(String),
+    #[error("Failed to gzip request body: {0}")]
+    Compress(std::io::Error),
+    #[error("Failed to write to stdout: {0}")]
+    Stdout(std::io::Error),
+}
+
+impl OtlpError {
+    /// Whether resending the same batch might succeed: transport failures
+    /// and 5xx responses, never 4xx. Throttled requests wait for the run
+    /// loop instead.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            OtlpError::Http(_) => true,
+            OtlpError::ServerError { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+/// Per-record attribute enrichment hook for library users.
+///
+/// Called with each entry and its attributes after the built-in mapping and
+/// before serialization, so it may add, rewrite or remove attributes. Every
+/// collector thread shares one hook, hence the `Send + Sync` bound.
+#[derive(Clone)]
+pub struct Enricher(Arc<EnrichFn>);
+
+type EnrichFn = dyn Fn(&JournalEntry, &mut Vec<KeyValue>) + Send + Sync;
+
+impl Enricher {
+    pub fn new(hook: impl Fn(&JournalEntry, &mut Vec<KeyValue>) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for Enricher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Enricher")
+    }
+}
+
+/// Standard OTLP/HTTP logs path.
+const DEFAULT_LOGS_PATH: &str = "/v1/logs";
+
+/// Build the logs URL from the configured endpoint.
+///
+/// With no `path`, `/v1/logs` is appended unless the endpoint already ends in
+/// it. An explicit `path` is always appended; an empty one uses the endpoint
+/// as-is.
+pub fn logs_url(endpoint: &str, path: Option<&str>) -> String {
+    join_url(endpoint, path, DEFAULT_LOGS_PATH)
+}
+
+/// Like [`logs_url`], with `default_path` in place of `/v1/logs`.
+fn join_url(endpoint: &str, path: Option<&str>, default_path: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    match path {
+        Some(path) if path.trim_matches('/').is_empty() => endpoint.to_string(),
+        Some(path) => format!("{}/{}", endpoint, path.trim_start_matches('/')),
+        None if endpoint.ends_with(default_path) => endpoint.to_string(),
+        None => format!("{}{}", endpoint, default_path),
+    }
+}
+
+/// Options that shape the OTLP payload, shared by every source.
+#[derive(Debug, Clone)]
+pub struct PayloadOptions {
+    /// OTLP or Loki push requests.
+    pub format: PayloadFormat,
+    /// OTLP over HTTP/JSON or gRPC/protobuf; gRPC needs the OTLP format.
+    pub protocol: OtlpProtocol,
+    /// Journal fields emitted once per `ResourceLogs` instead of on every record.
+    pub resource_fields: Vec<String>,
+    /// Journal field whose value groups records into `ResourceLogs` and
+    /// becomes `service.name`.
+    pub group_by: String,
+    /// Fields tried in order for records without `group_by`.
+    pub service_name_fallback: Vec<String>,
+    /// What goes into the log record body.
+    pub body_format: BodyFormat,
+    /// How a non-UTF-8 `MESSAGE` is put in the body.
+    pub binary_message: BinaryMessage,
+    /// Which journal timestamp becomes `time_unix_nano`.
+    pub timestamp_source: TimestampSource,
+    /// Field-name suffix rules typing extra fields, longest suffix first.
+    pub attribute_types: Vec<(String, AttributeType)>,
+    /// Type every unruled field holding a plain integer as `int`.
+    pub infer_int_fields: bool,
+    /// Value of the `os.type` resource attribute.
+    pub os_type: String,
+    /// Optional `os.description` resource attribute (e.g. distro name).
+    pub os_description: Option<String>,
+    /// Optional `os.version` resource attribute.
+    pub os_version: Option<String>,
+    /// Emit `CODE_FILE`/`CODE_LINE`/`CODE_FUNC` as `code.*` attributes.
+    pub code_attributes: bool,
+    /// Include `severity_number` on log records.
+    pub severity_number: bool,
+    /// Include `severity_text` on log records.
+    pub severity_text: bool,
+    /// Cap on extra journal fields per record; the rest are counted in
+    /// `dropped_attributes_count`.
+    pub max_extra_fields: Option<usize>,
+    /// Cap on log records per `ScopeLogs`; larger groups are split across
+    /// several `ResourceLogs` with the same resource.
+    pub max_records_per_scope: Option<usize>,
+    /// `forwarder.instance.id` resource attribute, if any.
+    pub instance_id: Option<String>,
+    /// Static resource attributes added for every source; source labels
+    /// override them.
+    pub resource_attributes: HashMap<String, String>,
+    /// Custom per-record attribute hook.
+    pub enricher: Option<Enricher>,
+}
+
+impl Default for PayloadOptions {
+    fn default() -> Self {
+        Self {
+            format: PayloadFormat::default(),
+            protocol: OtlpProtocol::default(),
+            resource_fields: Vec::new(),
+            group_by: DEFAULT_GROUP_BY.to_string(),
+            service_name_fallback: DEFAULT_SERVICE_NAME_FALLBACK
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
+            body_format: BodyFormat::default(),
+            binary_message: BinaryMessage::default(),
+            timestamp_source: TimestampSource::default(),
+            attribute_types: Vec::new(),
+            infer_int_fields: false,
+            os_type: DEFAULT_OS_TYPE.to_string(),
+            os_description: None,
+            os_version: None,
+            code_attributes: false,
+            severity_number: true,
+            severity_text: true,
+            max_extra_fields: None,
+            max_records_per_scope: None,
+            instance_id: None,
+            resource_attributes: HashMap::new(),
+            enricher: None,
+        }
+    }
+}
+
+/// Per-source settings for building records, resolved once when the
+/// source's collector is built.
+#[derive(Debug, Clone, Default)]
+pub struct SourcePayloadOptions {
+    /// Instrumentation scope name; `None` uses [`DEFAULT_SCOPE_NAME`].
+    pub scope_name: Option<String>,
+    /// Journal PRIORITY to OTLP severity.
+    pub severity_map: SeverityMap,
+}
+
+impl SourcePayloadOptions {
+    pub fn new(source: &crate::config::Source) -> Self {
+        Self {
+            scope_name: source.scope_name.clone(),
+            severity_map: SeverityMap::new(&source.severity_map),
+        }
+    }
+}
+
+/// One batch of entries for [`OtlpClient::send`].
+#[derive(Debug, Clone, Copy)]
+pub struct Batch<'a> {
+    pub entries: &'a [JournalEntry],
+    /// The batch following a 410 cursor reset; every record is tagged
+    /// `ojgf.after_cursor_reset = true`.
+    pub after_cursor_reset: bool,
+}
+
+impl<'a> Batch<'a> {
+    pub fn new(entries: &'a [JournalEntry]) -> Self {
+        Self {
+            entries,
+            after_cursor_reset: false,
+        }
+    }
+}
+
+/// Size at which the debug dump file is rotated to `<path>.1`.
+pub const DEBUG_DUMP_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Debugging tap that appends every forwarded log record to a JSON Lines
+/// file, rotating it once it exceeds a size bound.
+pub struct DebugDump {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Mutex<(File, u64)>,
 }
 
+impl DebugDump {
+    /// Open (or create) the dump file for appending.
+    pub fn open(path: &Path, max_bytes: u64) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            file: Mutex::new((file, len)),
+        })
+    }
+
+    /// Append one line per log record in `payload`, tagged with the source.
+    fn write(&self, source_name: &str, payload: &ExportLogsServiceRequest) -> std::io::Result<()> {
+        let mut guard = self.file.lock();
+        for record in payload
+            .resource_logs
+            .iter()
+            .flat_map(|r| &r.scope_logs)
+            .flat_map(|s| &s.log_records)
+        {
+            let mut line = serde_json::to_vec(&DumpLine {
+                source: source_name,
+                record,
+            })?;
+            line.push(b'\n');
+
+            if guard.1 + line.len() as u64 > self.max_bytes && guard.1 > 0 {
+                let mut rotated = self.path.clone().into_os_string();
+                rotated.push(".1");
+                std::fs::rename(&self.path, rotated)?;
+                guard.0 = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?;
+                guard.1 = 0;
+            }
+
+            guard.0.write_all(&line)?;
+            guard.1 += line.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct DumpLine<'a> {
+    source: &'a str,
+    record: &'a LogRecord,
+}
+
+/// Callback notified with the source name and how long its payload took to
+/// serialize.
+pub type SerializeObserver = Box<dyn Fn(&str, Duration) + Send + Sync>;
+
 /// OTLP client for sending logs
 pub struct OtlpClient {
     client: Client,
     endpoint: String,
+    options: PayloadOptions,
+    debug_dump: Option<DebugDump>,
+    pretty_payload_log: bool,
+    compression: OtlpCompression,
+    /// Set once the endpoint has rejected a compressed request.
+    compression_disabled: AtomicBool,
+    serialize_observer: Option<SerializeObserver>,
+    /// Write requests to stdout instead of POSTing them.
+    stdout: bool,
+    /// Header carrying a fresh UUID per request, if enabled.
+    request_id_header: Option<String>,
+    /// Once the flag is raised, requests are limited to the duration.
+    shutdown_timeout: Option<(Arc<AtomicBool>, Duration)>,
+    /// Where the batch's first and last cursor are recorded.
+    cursor_range: CursorRange,
+    /// Exports over gRPC instead of POSTing, for `otlp_protocol = "grpc"`.
+    grpc: Option<grpc::GrpcExporter>,
 }
 
 impl OtlpClient {
     /// Create a new OTLP client
+    ///
+    /// Logs are POSTed to `endpoint` joined with `path`; see [`logs_url`].
+    /// Loki payloads default to `/loki/api/v1/push` instead of `/v1/logs`.
+    /// Over gRPC, `endpoint` is used as-is and `path` is ignored.
     pub fn new(
         endpoint: &str,
+        path: Option<&str>,
         tls: Option<&crate::config::TlsConfig>,
         headers: &HashMap<String, String>,
+        timeouts: OtlpTimeouts,
+        options: PayloadOptions,
     ) -> Result<Self, OtlpError> {
-        let client = crate::config::build_http_client(tls, headers, REQUEST_TIMEOUT)
+        if !headers.is_empty() {
+            // Header values are often credentials; only the names are logged
+            let mut names: Vec<&str> = headers.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            debug!(headers = ?names, "OTLP request headers (values redacted)");
+        }
+        // reqwest's http2 feature (there for gatewayd sources) would let ALPN
+        // pick h2 for https endpoints; OTLP/HTTP stays on HTTP/1.1
+        let client = crate::config::http_client_builder(tls, headers, timeouts.request)
+            .map(|builder| builder.connect_timeout(timeouts.connect).http1_only())
+            .and_then(crate::config::finish_http_client)
             .map_err(|e| OtlpError::Config(e.to_string()))?;
 
-        // Normalize endpoint
-        let endpoint = endpoint.trim_end_matches('/').to_string();
-        let endpoint = format!("{}/v1/logs", endpoint);
+        let grpc = match (options.protocol, options.format) {
+            (OtlpProtocol::Http, _) => None,
+            (OtlpProtocol::Grpc, PayloadFormat::Otlp) => {
+                Some(grpc::GrpcExporter::new(endpoint, tls, headers, timeouts)?)
+            }
+            (OtlpProtocol::Grpc, PayloadFormat::Loki) => {
+                return Err(OtlpError::Config(
+                    "gRPC export needs the OTLP payload format".to_string(),
+                ));
+            }
+        };
+
+        let endpoint = match (options.protocol, options.format) {
+            (OtlpProtocol::Grpc, _) => endpoint.trim_end_matches('/').to_string(),
+            (_, PayloadFormat::Otlp) => logs_url(endpoint, path),
+            (_, PayloadFormat::Loki) => join_url(endpoint, path, crate::loki::DEFAULT_PUSH_PATH),
+        };
+
+        Ok(Self {
+            client,
+            endpoint,
+            options,
+            debug_dump: None,
+            pretty_payload_log: false,
+            compression: OtlpCompression::None,
+            compression_disabled: AtomicBool::new(false),
+            serialize_observer: None,
+            stdout: false,
+            request_id_header: None,
+            shutdown_timeout: None,
+            cursor_range: CursorRange::Off,
+            grpc,
+        })
+    }
+
+    /// Compress request bodies; a 415 reply turns compression off for the
+    /// rest of the session.
+    pub fn with_compression(mut self, compression: OtlpCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Attach a random UUID in `header` to every request, and log it with
+    /// the batch's cursor range, for matching pushes with backend logs.
+    pub fn with_request_id_header(mut self, header: impl Into<String>) -> Self {
+        self.request_id_header = Some(header.into());
+        self
+    }
+
+    /// Record the first and last cursor of each batch as request headers or
+    /// scope attributes.
+    pub fn with_cursor_range(mut self, cursor_range: CursorRange) -> Self {
+        self.cursor_range = cursor_range;
+        self
+    }
+
+    /// Limit requests started after `shutdown` is raised to `timeout`, so
+    /// final flushes finish (or give up) within the shutdown grace.
+    pub fn with_shutdown_timeout(mut self, shutdown: Arc<AtomicBool>, timeout: Duration) -> Self {
+        self.shutdown_timeout = Some((shutdown, timeout));
+        self
+    }
+
+    /// The shutdown request limit, once shutdown has begun.
+    fn active_shutdown_timeout(&self) -> Option<Duration> {
+        self.shutdown_timeout
+            .as_ref()
+            .filter(|(shutdown, _)| shutdown.load(Ordering::Relaxed))
+            .map(|(_, timeout)| *timeout)
+    }
+
+    /// POST the serialized payload with per-request `headers`, gzip-encoding
+    /// it when `compress` is set.
+    fn post(
+        &self,
+        json: &str,
+        compress: bool,
+        headers: &[(&str, &str)],
+    ) -> Result<reqwest::blocking::Response, OtlpError> {
+        let mut request = self
+            .client
+            .post(&self.endpoint)
+            .header("Content-Type", "application/json");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        if let Some(timeout) = self.active_shutdown_timeout() {
+            request = request.timeout(timeout);
+        }
+        let request = if compress {
+            let body = gzip(json).map_err(OtlpError::Compress)?;
+            request.header("Content-Encoding", "gzip").body(body)
+        } else {
+            request.body(json.to_string())
+        };
+        Ok(request.send()?)
+    }
+
+    /// Pretty-print the payload in trace logs; the wire format stays compact.
+    pub fn with_pretty_payload_log(mut self, pretty: bool) -> Self {
+        self.pretty_payload_log = pretty;
+        self
+    }
+
+    /// Run `enricher` on every log record's attributes before sending.
+    pub fn with_enricher(mut self, enricher: Enricher) -> Self {
+        self.options.enricher = Some(enricher);
+        self
+    }
+
+    /// Report payload serialization time (e.g. to a metrics gauge).
+    pub fn with_serialize_observer(mut self, observer: SerializeObserver) -> Self {
+        self.serialize_observer = Some(observer);
+        self
+    }
+
+    /// Write each request as one line of OTLP JSON to stdout instead of
+    /// POSTing it, for piping into `jq` or another shipper.
+    pub fn with_stdout(mut self) -> Self {
+        self.stdout = true;
+        self
+    }
 
-        Ok(Self { client, endpoint })
+    /// Also append every successfully sent record to `dump`.
+    pub fn with_debug_dump(mut self, dump: DebugDump) -> Self {
+        self.debug_dump = Some(dump);
+        self
     }
 
     /// Send log records to the OTLP endpoint
+    ///
+    /// `source_name` identifies the source in metrics and debug dumps;
+    /// `host_name` is reported as the `host.name` resource attribute.
     pub fn send(
         &self,
         source_name: &str,
-        entries: &[JournalEntry],
+        host_name: &str,
+        batch: Batch<'_>,
         labels: &HashMap<String, String>,
+        source: &SourcePayloadOptions,
     ) -> Result<(), OtlpError> {
+        let Batch {
+            entries,
+            after_cursor_reset,
+        } = batch;
         if entries.is_empty() {
             return Ok(());
         }
 
-        let payload = build_otlp_payload(source_name, entries, labels);
-        let json = serde_json::to_string(&payload).expect("Failed to serialize OTLP payload");
+        let payload = match self.options.format {
+            PayloadFormat::Otlp => {
+                let mut payload =
+                    build_otlp_payload(host_name, entries, labels, source, &self.options);
+                if after_cursor_reset {
+                    mark_after_cursor_reset(&mut payload);
+                }
+                if self.cursor_range == CursorRange::Attributes {
+                    mark_cursor_range(&mut payload, entries);
+                }
+                WirePayload::Otlp(payload)
+            }
+            PayloadFormat::Loki => WirePayload::Loki(crate::loki::build_push_request(
+                host_name,
+                entries,
+                labels,
+                &self.options,
+            )),
+        };
+        let serialize_start = std::time::Instant::now();
+        let body = match (&self.grpc, &payload) {
+            (Some(grpc), WirePayload::Otlp(request)) if !self.stdout => {
+                RequestBody::Protobuf(grpc, grpc::proto::ExportLogsServiceRequest::from(request))
+            }
+            _ => RequestBody::Json(
+                serde_json::to_string(&payload).expect("Failed to serialize OTLP payload"),
+            ),
+        };
+        if let Some(observer) = &self.serialize_observer {
+            observer(source_name, serialize_start.elapsed());
+        }
 
         trace!(endpoint = %self.endpoint, records = entries.len(), "Sending OTLP logs");
+        if tracing::enabled!(tracing::Level::TRACE) {
+            // Pretty-printing is a second serialization, so only pay for it here
+            if self.pretty_payload_log {
+                let pretty = serde_json::to_string_pretty(&payload)
+                    .expect("Failed to serialize OTLP payload");
+                trace!(payload = %pretty, "OTLP payload");
+            } else {
+                match &body {
+                    RequestBody::Json(json) => trace!(payload = %json, "OTLP payload"),
+                    RequestBody::Protobuf(_, request) => trace!(payload = ?request, "OTLP payload"),
+                }
+            }
+        }
 
-        let response = self
-            .client
-            .post(&self.endpoint)
-            .header("Content-Type", "application/json")
-            .body(json)
-            .send()?;
+        if let (true, RequestBody::Json(json)) = (self.stdout, &body) {
+            // One locked write per request keeps lines from interleaving
+            // between collector threads
+            let mut stdout = std::io::stdout().lock();
+            writeln!(stdout, "{}", json)
+                .and_then(|_| stdout.flush())
+                .map_err(OtlpError::Stdout)?;
+            self.write_debug_dump(source_name, &payload, host_name, entries, labels, source);
+            return Ok(());
+        }
+
+        // The 415 retry below is the same logical request, so it keeps the ID
+        let request_id = self.request_id_header.as_ref().map(|_| new_request_id());
+        if let Some(id) = &request_id {
+            debug!(
+                source = source_name,
+                request_id = %id,
+                first_cursor = %entries[0].cursor,
+                last_cursor = %entries[entries.len() - 1].cursor,
+                "Sending OTLP request"
+            );
+        }
+
+        let mut headers = Vec::new();
+        if let (Some(header), Some(id)) = (&self.request_id_header, &request_id) {
+            headers.push((header.as_str(), id.as_str()));
+        }
+        if self.cursor_range == CursorRange::Headers {
+            headers.push((FIRST_CURSOR_HEADER, entries[0].cursor.as_str()));
+            headers.push((
+                LAST_CURSOR_HEADER,
+                entries[entries.len() - 1].cursor.as_str(),
+            ));
+        }
+
+        let compress = self.compression == OtlpCompression::Gzip
+            && !self.compression_disabled.load(Ordering::Relaxed);
+        let json = match body {
+            RequestBody::Json(json) => json,
+            RequestBody::Protobuf(grpc, request) => {
+                grpc.export(request, &headers, compress, self.active_shutdown_timeout())?;
+                debug!(
+                    records = entries.len(),
+                    "Successfully sent logs to OTLP endpoint"
+                );
+                self.write_debug_dump(source_name, &payload, host_name, entries, labels, source);
+                return Ok(());
+            }
+        };
+        let mut response = self.post(&json, compress, &headers)?;
+
+        if compress && response.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE {
+            warn!(
+                endpoint = %self.endpoint,
+                "OTLP endpoint rejected gzip (415), disabling compression for this session"
+            );
+            self.compression_disabled.store(true, Ordering::Relaxed);
+            response = self.post(&json, false, &headers)?;
+        }
 
         let status = response.status();
 
@@ -78,19 +625,127 @@ impl OtlpClient {
                 records = entries.len(),
                 "Successfully sent logs to OTLP endpoint"
             );
+            self.write_debug_dump(source_name, &payload, host_name, entries, labels, source);
             Ok(())
         } else {
+            let retry_after = matches!(
+                status,
+                StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+            )
+            .then(|| response.headers().get(reqwest::header::RETRY_AFTER))
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, SystemTime::now()));
             let body = response.text().unwrap_or_default();
-            warn!(status = %status, body = %body, "OTLP endpoint rejected request");
-            Err(OtlpError::ServerError { status, body })
+            warn!(
+                status = %status,
+                body = %body,
+                retry_after = ?retry_after,
+                request_id = request_id.as_deref().unwrap_or("-"),
+                "OTLP endpoint rejected request"
+            );
+            Err(match retry_after {
+                Some(retry_after) => OtlpError::Throttled { retry_after },
+                None => OtlpError::ServerError { status, body },
+            })
+        }
+    }
+
+    /// Dump the sent records; Loki batches are dumped as the OTLP records
+    /// they would have been.
+    fn write_debug_dump(
+        &self,
+        source_name: &str,
+        payload: &WirePayload,
+        host_name: &str,
+        entries: &[JournalEntry],
+        labels: &HashMap<String, String>,
+        source: &SourcePayloadOptions,
+    ) {
+        let Some(dump) = &self.debug_dump else {
+            return;
+        };
+        let result = match payload {
+            WirePayload::Otlp(payload) => dump.write(source_name, payload),
+            WirePayload::Loki(_) => dump.write(
+                source_name,
+                &build_otlp_payload(host_name, entries, labels, source, &self.options),
+            ),
+        };
+        if let Err(e) = result {
+            warn!(error = %e, "Failed to write debug dump");
         }
     }
 }
 
+/// Gzip the serialized payload for a `Content-Encoding: gzip` body.
+fn gzip(json: &str) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(json.as_bytes())?;
+    encoder.finish()
+}
+
+/// Parse a `Retry-After` value, either delay seconds or an HTTP-date
+/// (`Sun, 06 Nov 1994 08:49:37 GMT`); a date in the past means no wait.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let [_, day, month, year, time, "GMT"] = value.split_whitespace().collect::<Vec<_>>()[..]
+    else {
+        return None;
+    };
+    let month = MONTHS.iter().position(|m| *m == month)? + 1;
+    let at =
+        humantime::parse_rfc3339(&format!("{}-{:02}-{:0>2}T{}Z", year, month, day, time)).ok()?;
+    Some(at.duration_since(now).unwrap_or_default())
+}
+
+/// Random (version 4) UUID in its hyphenated form.
+fn new_request_id() -> String {
+    use ring::rand::SecureRandom;
+    let mut bytes = [0u8; 16];
+    // A zeroed ID only weakens correlation, never the request itself
+    let _ = ring::rand::SystemRandom::new().fill(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// A payload encoded for the configured transport.
+enum RequestBody<'a> {
+    Json(String),
+    Protobuf(
+        &'a grpc::GrpcExporter,
+        grpc::proto::ExportLogsServiceRequest,
+    ),
+}
+
 // ============================================================================
 // OTLP Protocol Structures
 // ============================================================================
 
+/// Request body in the configured [`PayloadFormat`].
+#[derive(Serialize)]
+#[serde(untagged)]
+enum WirePayload {
+    Otlp(ExportLogsServiceRequest),
+    Loki(crate::loki::PushRequest),
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct ExportLogsServiceRequest {
@@ -116,10 +771,12 @@ struct ScopeLogs {
     log_records: Vec<LogRecord>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct Scope {
     name: String,
     version: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attributes: Vec<KeyValue>,
 }
 
 #[derive(Serialize)]
@@ -127,239 +784,600 @@ struct Scope {
 struct LogRecord {
     time_unix_nano: String,
     observed_time_unix_nano: String,
-    severity_number: u8,
-    severity_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    severity_number: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    severity_text: Option<Arc<str>>,
     body: AnyValue,
     attributes: Vec<KeyValue>,
+    #[serde(skip_serializing_if = "is_zero")]
+    dropped_attributes_count: u32,
 }
 
-#[derive(Serialize)]
-struct KeyValue {
-    key: String,
-    value: AttributeValue,
+fn is_zero(n: &u32) -> bool {
+    *n == 0
 }
 
-#[derive(Serialize)]
+/// A log record or resource attribute.
+#[derive(Serialize, Debug, Clone)]
+pub struct KeyValue {
+    pub key: String,
+    pub value: AttributeValue,
+}
+
+/// OTLP attribute value; build one with the typed constructors.
+#[derive(Serialize, Default, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
-struct AttributeValue {
+pub struct AttributeValue {
     #[serde(skip_serializing_if = "Option::is_none")]
     string_value: Option<String>,
+    /// int64 values are strings in OTLP/JSON.
     #[serde(skip_serializing_if = "Option::is_none")]
     int_value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bool_value: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    double_value: Option<f64>,
+}
+
+impl AttributeValue {
+    pub fn string(value: impl Into<String>) -> Self {
+        Self {
+            string_value: Some(value.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn int(value: impl ToString) -> Self {
+        Self {
+            int_value: Some(value.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn bool(value: bool) -> Self {
+        Self {
+            bool_value: Some(value),
+            ..Default::default()
+        }
+    }
+
+    pub fn double(value: f64) -> Self {
+        Self {
+            double_value: Some(value),
+            ..Default::default()
+        }
+    }
+
+    /// The value if this is a string attribute.
+    pub fn as_str(&self) -> Option<&str> {
+        self.string_value.as_deref()
+    }
+
+    /// Parse `value` as `ty`, or `None` if it doesn't fit.
+    fn typed(ty: AttributeType, value: &str) -> Option<Self> {
+        match ty {
+            AttributeType::String => Some(Self::string(value)),
+            AttributeType::Int => value.trim().parse::<i64>().ok().map(Self::int),
+            AttributeType::Bool => match value.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" => Some(Self::bool(true)),
+                "false" | "0" | "no" => Some(Self::bool(false)),
+                _ => None,
+            },
+            AttributeType::Double => value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .map(Self::double),
+        }
+    }
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct AnyValue {
-    string_value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    string_value: Option<String>,
+    /// bytes values are base64 strings in OTLP/JSON.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "base64::serialize"
+    )]
+    bytes_value: Option<Vec<u8>>,
+}
+
+impl AnyValue {
+    fn string(value: String) -> Self {
+        Self {
+            string_value: Some(value),
+            bytes_value: None,
+        }
+    }
+
+    fn bytes(value: &[u8]) -> Self {
+        Self {
+            string_value: None,
+            bytes_value: Some(value.to_vec()),
+        }
+    }
+}
+
+// Standard base64 (with padding) for OTLP/JSON bytes values
+pub(crate) mod base64 {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    pub fn encode(bytes: &[u8]) -> String {
+        let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    result.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+                } else {
+                    result.push('=');
+                }
+            }
+        }
+        result
+    }
+
+    pub fn serialize<S: serde::Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_str(&encode(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
 }
 
 // ============================================================================
 // Payload Building
 // ============================================================================
 
+/// Journal fields the record builder emits directly, with their attribute keys.
+/// Fields not listed here use the generic lowercase-dot transform.
+const FIELD_ATTRIBUTE_KEYS: &[(&str, &str)] = &[
+    ("_PID", "process.pid"),
+    ("_UID", "process.owner"),
+    ("_GID", "process.group.id"),
+    ("_COMM", "process.command"),
+    ("_EXE", "process.executable.path"),
+    ("SYSLOG_IDENTIFIER", "syslog.identifier"),
+    ("_BOOT_ID", "systemd.boot_id"),
+    ("_SYSTEMD_UNIT", "journald.unit.name"),
+    ("_MACHINE_ID", "host.id"),
+];
+
+/// Journal fields that always hold integers, sent as `int` attributes when
+/// no `attribute_types` rule matches. Only listed fields are coerced, so a
+/// value that merely looks numeric (a version, a hex ID) stays a string.
+const INT_FIELDS: &[&str] = &[
+    "_PID",
+    "_UID",
+    "_GID",
+    "_AUDIT_SESSION",
+    "_AUDIT_LOGINUID",
+    "_SYSTEMD_OWNER_UID",
+    "SYSLOG_PID",
+    "SYSLOG_FACILITY",
+    "ERRNO",
+    "TID",
+    "CODE_LINE",
+    "EXIT_STATUS",
+    "OBJECT_PID",
+    "OBJECT_UID",
+    "OBJECT_GID",
+    "OBJECT_AUDIT_SESSION",
+    "OBJECT_AUDIT_LOGINUID",
+    "COREDUMP_PID",
+    "COREDUMP_UID",
+    "COREDUMP_GID",
+    "COREDUMP_SIGNAL",
+];
+
+/// Attribute key used for a journal field name.
+fn field_attribute_key(field: &str) -> String {
+    FIELD_ATTRIBUTE_KEYS
+        .iter()
+        .find(|(name, _)| *name == field)
+        .map(|(_, key)| key.to_string())
+        .unwrap_or_else(|| field.to_lowercase().replace('_', "."))
+}
+
+/// Instrumentation scope name used when a source does not override it.
+pub const DEFAULT_SCOPE_NAME: &str = env!("CARGO_PKG_NAME");
+
 fn build_otlp_payload(
-    source_name: &str,
+    host_name: &str,
     entries: &[JournalEntry],
     labels: &HashMap<String, String>,
+    source: &SourcePayloadOptions,
+    options: &PayloadOptions,
 ) -> ExportLogsServiceRequest {
-    // Group entries by service (systemd unit)
-    let mut by_service: HashMap<String, Vec<&JournalEntry>> = HashMap::new();
+    // Group entries by service (the `group_by` field, systemd unit by
+    // default, then the fallback chain) and resource-scoped field values
+    type GroupKey = (String, Vec<Option<String>>);
+    let mut by_service: HashMap<GroupKey, Vec<&JournalEntry>> = HashMap::new();
     for entry in entries {
-        let service = entry
-            .systemd_unit
-            .clone()
-            .unwrap_or_else(|| "unknown".to_string());
-        by_service.entry(service).or_default().push(entry);
+        let service = std::iter::once(&options.group_by)
+            .chain(&options.service_name_fallback)
+            .find_map(|field| entry.field(field).filter(|v| !v.is_empty()))
+            .unwrap_or("unknown")
+            .to_string();
+        let resource_values = options
+            .resource_fields
+            .iter()
+            .map(|field| entry.field(field).map(str::to_string))
+            .collect();
+        by_service
+            .entry((service, resource_values))
+            .or_default()
+            .push(entry);
+    }
+
+    // Source-level attributes are the same for every group in the batch, so
+    // build them (and the scope) once and clone per resource. OTLP has no way
+    // to share a resource between ResourceLogs, so each still serializes them.
+    let mut common_attrs = vec![
+        KeyValue {
+            key: "host.name".to_string(),
+            value: AttributeValue::string(host_name.to_string()),
+        },
+        KeyValue {
+            key: "os.type".to_string(),
+            value: AttributeValue::string(options.os_type.clone()),
+        },
+    ];
+
+    for (key, value) in [
+        ("os.description", &options.os_description),
+        ("os.version", &options.os_version),
+        ("forwarder.instance.id", &options.instance_id),
+    ] {
+        if let Some(value) = value {
+            common_attrs.push(KeyValue {
+                key: key.to_string(),
+                value: AttributeValue::string(value.clone()),
+            });
+        }
+    }
+
+    // Forwarder-wide attributes, then custom labels; each replaces an earlier
+    // attribute of the same key
+    common_attrs.retain(|attr| !options.resource_attributes.contains_key(&attr.key));
+    for (key, value) in &options.resource_attributes {
+        common_attrs.push(KeyValue {
+            key: key.clone(),
+            value: AttributeValue::string(value.clone()),
+        });
     }
+    common_attrs.retain(|attr| !labels.contains_key(&attr.key));
+    let label_attrs: Vec<KeyValue> = labels
+        .iter()
+        .map(|(key, value)| KeyValue {
+            key: key.clone(),
+            value: AttributeValue::string(value.clone()),
+        })
+        .collect();
 
-    let mut resource_logs = Vec::new();
+    let scope = Scope {
+        name: source
+            .scope_name
+            .as_deref()
+            .unwrap_or(DEFAULT_SCOPE_NAME)
+            .to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        attributes: Vec::new(),
+    };
 
-    for (service, service_entries) in by_service {
+    let mut resource_logs = Vec::with_capacity(by_service.len());
+
+    for ((service, resource_values), service_entries) in by_service {
         // Build resource attributes
-        let mut resource_attrs = vec![
-            KeyValue {
-                key: "host.name".to_string(),
-                value: AttributeValue {
-                    string_value: Some(source_name.to_string()),
-                    int_value: None,
-                },
-            },
-            KeyValue {
-                key: "service.name".to_string(),
-                value: AttributeValue {
-                    string_value: Some(service),
-                    int_value: None,
-                },
-            },
-            KeyValue {
-                key: "os.type".to_string(),
-                value: AttributeValue {
-                    string_value: Some("linux".to_string()),
-                    int_value: None,
-                },
-            },
-        ];
+        let mut group_attrs = vec![KeyValue {
+            key: "service.name".to_string(),
+            value: AttributeValue::string(service),
+        }];
+
+        // Add journal fields promoted to the resource
+        for (field, value) in options.resource_fields.iter().zip(resource_values) {
+            if let Some(value) = value {
+                group_attrs.push(KeyValue {
+                    key: field_attribute_key(field),
+                    value: typed_field_value(field, &value, options),
+                });
+            }
+        }
+        group_attrs.retain(|attr| !labels.contains_key(&attr.key));
+
+        let mut resource_attrs =
+            Vec::with_capacity(common_attrs.len() + group_attrs.len() + label_attrs.len());
+        resource_attrs.extend(common_attrs.iter().cloned());
+        resource_attrs.append(&mut group_attrs);
+        resource_attrs.extend(label_attrs.iter().cloned());
+
+        // Build log records, split into separate ResourceLogs when a group
+        // exceeds the per-scope record cap
+        let chunk_size = options
+            .max_records_per_scope
+            .unwrap_or(service_entries.len())
+            .max(1);
+        for chunk in service_entries.chunks(chunk_size) {
+            let log_records: Vec<LogRecord> = chunk
+                .iter()
+                .map(|entry| build_log_record(entry, &source.severity_map, options))
+                .collect();
 
-        // Add custom labels
-        for (key, value) in labels {
-            resource_attrs.push(KeyValue {
-                key: key.clone(),
-                value: AttributeValue {
-                    string_value: Some(value.clone()),
-                    int_value: None,
+            resource_logs.push(ResourceLogs {
+                resource: Resource {
+                    attributes: resource_attrs.clone(),
                 },
+                scope_logs: vec![ScopeLogs {
+                    scope: scope.clone(),
+                    log_records,
+                }],
             });
         }
+    }
 
-        // Build log records
-        let log_records: Vec<LogRecord> =
-            service_entries.into_iter().map(build_log_record).collect();
+    ExportLogsServiceRequest { resource_logs }
+}
 
-        resource_logs.push(ResourceLogs {
-            resource: Resource {
-                attributes: resource_attrs,
-            },
-            scope_logs: vec![ScopeLogs {
-                scope: Scope {
-                    name: "otel-journal-gatewayd-forwarder".to_string(),
-                    version: env!("CARGO_PKG_VERSION").to_string(),
-                },
-                log_records,
-            }],
+/// Tag every record in `payload` as forwarded right after a cursor reset,
+/// where entries may overlap or leave a gap with what was sent before.
+fn mark_after_cursor_reset(payload: &mut ExportLogsServiceRequest) {
+    let records = payload
+        .resource_logs
+        .iter_mut()
+        .flat_map(|r| r.scope_logs.iter_mut())
+        .flat_map(|s| s.log_records.iter_mut());
+    for record in records {
+        record.attributes.push(KeyValue {
+            key: "ojgf.after_cursor_reset".to_string(),
+            value: AttributeValue::bool(true),
         });
     }
+}
 
-    ExportLogsServiceRequest { resource_logs }
+/// Headers carrying the batch's cursor range with `cursor_range = "headers"`.
+const FIRST_CURSOR_HEADER: &str = "X-OJGF-First-Cursor";
+const LAST_CURSOR_HEADER: &str = "X-OJGF-Last-Cursor";
+
+/// Add the batch's first and last cursor to every scope, so each request
+/// records exactly which journal range it covered.
+fn mark_cursor_range(payload: &mut ExportLogsServiceRequest, entries: &[JournalEntry]) {
+    let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+        return;
+    };
+    let scopes = payload
+        .resource_logs
+        .iter_mut()
+        .flat_map(|r| r.scope_logs.iter_mut());
+    for scope_logs in scopes {
+        scope_logs.scope.attributes.extend([
+            KeyValue {
+                key: "ojgf.cursor.first".to_string(),
+                value: AttributeValue::string(first.cursor.clone()),
+            },
+            KeyValue {
+                key: "ojgf.cursor.last".to_string(),
+                value: AttributeValue::string(last.cursor.clone()),
+            },
+        ]);
+    }
 }
 
-fn build_log_record(entry: &JournalEntry) -> LogRecord {
+fn build_log_record(
+    entry: &JournalEntry,
+    severity_map: &SeverityMap,
+    options: &PayloadOptions,
+) -> LogRecord {
+    let source_time = match options.timestamp_source {
+        TimestampSource::Source => entry.source_realtime_timestamp(),
+        TimestampSource::Journald => None,
+    };
     // Convert microseconds to nanoseconds
-    let time_unix_nano = entry.realtime_timestamp * 1000;
+    let time_unix_nano = source_time.unwrap_or(entry.realtime_timestamp) * 1000;
     let now_ns = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_nanos() as u64;
 
-    let (severity_number, severity_text) = map_priority(entry.priority);
+    let &(severity_number, ref severity_text) = severity_map.level(entry.priority);
 
-    // Build attributes from journal fields
+    // Build attributes from journal fields, skipping those promoted to the resource
+    let on_record = |field: &str| !options.resource_fields.iter().any(|f| f == field);
     let mut attributes = Vec::new();
 
-    if let Some(ref pid) = entry.pid {
-        attributes.push(KeyValue {
-            key: "process.pid".to_string(),
-            value: AttributeValue {
-                string_value: Some(pid.clone()),
-                int_value: None,
-            },
-        });
-    }
-
-    if let Some(ref uid) = entry.uid {
-        attributes.push(KeyValue {
-            key: "process.owner".to_string(),
-            value: AttributeValue {
-                string_value: Some(uid.clone()),
-                int_value: None,
-            },
-        });
+    for (field, value) in [
+        ("_PID", &entry.pid),
+        ("_UID", &entry.uid),
+        ("_GID", &entry.gid),
+    ] {
+        if let Some(value) = value
+            && on_record(field)
+        {
+            attributes.push(KeyValue {
+                key: field_attribute_key(field),
+                value: typed_field_value(field, value, options),
+            });
+        }
     }
 
-    if let Some(ref comm) = entry.comm {
+    if let Some(ref comm) = entry.comm
+        && on_record("_COMM")
+    {
         attributes.push(KeyValue {
             key: "process.command".to_string(),
-            value: AttributeValue {
-                string_value: Some(comm.clone()),
-                int_value: None,
-            },
+            value: AttributeValue::string(comm.clone()),
         });
     }
 
-    if let Some(ref exe) = entry.exe {
+    if let Some(ref exe) = entry.exe
+        && on_record("_EXE")
+    {
         attributes.push(KeyValue {
             key: "process.executable.path".to_string(),
-            value: AttributeValue {
-                string_value: Some(exe.clone()),
-                int_value: None,
-            },
+            value: AttributeValue::string(exe.clone()),
         });
     }
 
-    if let Some(ref syslog_id) = entry.syslog_identifier {
+    if let Some(ref syslog_id) = entry.syslog_identifier
+        && on_record("SYSLOG_IDENTIFIER")
+    {
         attributes.push(KeyValue {
             key: "syslog.identifier".to_string(),
-            value: AttributeValue {
-                string_value: Some(syslog_id.clone()),
-                int_value: None,
-            },
+            value: AttributeValue::string(syslog_id.clone()),
         });
     }
 
-    if let Some(ref boot_id) = entry.boot_id {
+    if let Some(ref boot_id) = entry.boot_id
+        && on_record("_BOOT_ID")
+    {
         attributes.push(KeyValue {
             key: "systemd.boot_id".to_string(),
-            value: AttributeValue {
-                string_value: Some(boot_id.clone()),
-                int_value: None,
-            },
+            value: AttributeValue::string(boot_id.clone()),
         });
     }
 
-    if let Some(ref unit) = entry.systemd_unit {
+    if let Some(ref unit) = entry.systemd_unit
+        && on_record("_SYSTEMD_UNIT")
+    {
         attributes.push(KeyValue {
             key: "journald.unit.name".to_string(),
-            value: AttributeValue {
-                string_value: Some(unit.clone()),
-                int_value: None,
-            },
+            value: AttributeValue::string(unit.clone()),
         });
     }
 
     if let Some(priority) = entry.priority {
         attributes.push(KeyValue {
             key: "journald.priority.number".to_string(),
-            value: AttributeValue {
-                string_value: None,
-                int_value: Some(priority.to_string()),
-            },
+            value: AttributeValue::int(priority),
+        });
+    }
+
+    // Keep the journald receive time when the record time came from the app
+    if source_time.is_some() {
+        attributes.push(KeyValue {
+            key: "journald.realtime_timestamp".to_string(),
+            value: AttributeValue::int(entry.realtime_timestamp),
         });
     }
 
     // Add journal cursor as attribute (useful for debugging)
     attributes.push(KeyValue {
         key: "systemd.cursor".to_string(),
-        value: AttributeValue {
-            string_value: Some(entry.cursor.clone()),
-            int_value: None,
-        },
+        value: AttributeValue::string(entry.cursor.clone()),
     });
 
-    // Add extra fields
-    for (key, value) in &entry.extra_fields {
+    // Add extra fields, keeping the first `max_extra_fields` by name
+    let mut extra_fields: Vec<(&String, &String)> = entry
+        .extra_fields
+        .iter()
+        .filter(|(key, _)| on_record(key))
+        .collect();
+    let mut dropped_attributes_count = 0;
+    if let Some(max) = options.max_extra_fields
+        && extra_fields.len() > max
+    {
+        extra_fields.sort_unstable_by_key(|(key, _)| *key);
+        dropped_attributes_count = (extra_fields.len() - max) as u32;
+        extra_fields.truncate(max);
+    }
+    for (key, value) in extra_fields {
+        if options.code_attributes
+            && let Some(attr) = code_attribute(key, value)
+        {
+            attributes.push(attr);
+            continue;
+        }
         // Convert journal field names to something more reasonable
-        let attr_key = key.to_lowercase().replace('_', ".");
+        let attr_key = field_attribute_key(key);
         attributes.push(KeyValue {
             key: attr_key,
-            value: AttributeValue {
-                string_value: Some(value.clone()),
-                int_value: None,
-            },
+            value: typed_field_value(key, value, options),
         });
     }
 
+    if let Some(enricher) = &options.enricher {
+        (enricher.0)(entry, &mut attributes);
+    }
+
     LogRecord {
         time_unix_nano: time_unix_nano.to_string(),
         observed_time_unix_nano: now_ns.to_string(),
-        severity_number,
-        severity_text: severity_text.to_string(),
-        body: AnyValue {
-            string_value: entry.message.clone(),
+        severity_number: options.severity_number.then_some(severity_number),
+        severity_text: options.severity_text.then(|| severity_text.clone()),
+        body: match (options.body_format, &entry.raw, &entry.message_bytes) {
+            (BodyFormat::RawJson, Some(raw), _) => AnyValue::string(raw.clone()),
+            (_, _, Some(bytes)) if options.binary_message == BinaryMessage::Bytes => {
+                AnyValue::bytes(bytes)
+            }
+            _ => AnyValue::string(entry.message.clone()),
         },
         attributes,
+        dropped_attributes_count,
     }
 }
 
+/// Map `CODE_FILE`/`CODE_LINE`/`CODE_FUNC` to OTel `code.*` semantic
+/// convention attributes, with `code.lineno` as an int when it parses.
+fn code_attribute(field: &str, value: &str) -> Option<KeyValue> {
+    let (key, value) = match field {
+        "CODE_FILE" => ("code.filepath", AttributeValue::string(value)),
+        "CODE_FUNC" => ("code.function", AttributeValue::string(value)),
+        "CODE_LINE" => (
+            "code.lineno",
+            AttributeValue::typed(AttributeType::Int, value)
+                .unwrap_or_else(|| AttributeValue::string(value)),
+        ),
+        _ => return None,
+    };
+    Some(KeyValue {
+        key: key.to_string(),
+        value,
+    })
+}
+
+/// Type a journal field by the first matching suffix rule, else as an int
+/// if it is in [`INT_FIELDS`] (or, with `infer_int_fields`, holds a plain
+/// integer), falling back to a string when neither applies or the value
+/// doesn't parse.
+fn typed_field_value(field: &str, value: &str, options: &PayloadOptions) -> AttributeValue {
+    let Some((suffix, ty)) = options
+        .attribute_types
+        .iter()
+        .find(|(suffix, _)| field.ends_with(suffix.as_str()))
+    else {
+        let int =
+            INT_FIELDS.contains(&field) || (options.infer_int_fields && is_plain_integer(value));
+        return int
+            .then(|| AttributeValue::typed(AttributeType::Int, value))
+            .flatten()
+            .unwrap_or_else(|| AttributeValue::string(value));
+    };
+    AttributeValue::typed(*ty, value).unwrap_or_else(|| {
+        debug!(field = %field, suffix = %suffix, ty = ?ty, "Field value does not match inferred type, sending as string");
+        AttributeValue::string(value)
+    })
+}
+
+/// A decimal integer in canonical form: an optional `-` and digits without
+/// leading zeros, so IDs like `007` or `+1` keep their exact text.
+fn is_plain_integer(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    !digits.is_empty()
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        && (digits == "0" || !digits.starts_with('0'))
+        && value != "-0"
+}
+
 /// Map journal PRIORITY to OTLP severity
 ///
 /// | Journal PRIORITY | OTLP Severity |
@@ -372,7 +1390,7 @@ fn build_log_record(entry: &JournalEntry) -> LogRecord {
 /// | 5 (notice)       | 9 (INFO)      |
 /// | 6 (info)         | 9 (INFO)      |
 /// | 7 (debug)        | 5 (DEBUG)     |
-fn map_priority(priority: Option<u8>) -> (u8, &'static str) {
+pub(crate) fn map_priority(priority: Option<u8>) -> (u8, &'static str) {
     match priority {
         Some(0) | Some(1) => (21, "FATAL"),
         Some(2) | Some(3) => (17, "ERROR"),
@@ -384,9 +1402,81 @@ fn map_priority(priority: Option<u8>) -> (u8, &'static str) {
     }
 }
 
-#[cfg(test)]
-mod tests {
+/// OTLP short name for a severity number's range: `TRACE` for 1-4, `DEBUG`
+/// for 5-8, up to `FATAL` for 21-24.
+pub(crate) fn severity_name(number: u8) -> &'static str {
+    match number {
+        0 => "UNSPECIFIED",
+        1..=4 => "TRACE",
+        5..=8 => "DEBUG",
+        9..=12 => "INFO",
+        13..=16 => "WARN",
+        17..=20 => "ERROR",
+        _ => "FATAL",
+    }
+}
+
+/// A source's journal PRIORITY to OTLP severity table: [`map_priority`] with
+/// the source's `severity_map` overrides applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeverityMap {
+    /// Severity number and text, indexed by priority. The text is shared
+    /// with every record rather than copied into each.
+    levels: [(u8, Arc<str>); 8],
+    /// For a missing or out-of-range priority.
+    unspecified: (u8, Arc<str>),
+}
+
+impl SeverityMap {
+    /// Apply `overrides` (keyed by priority, as validated in config) to the
+    /// built-in table.
+    pub fn new(overrides: &BTreeMap<String, SeverityLevel>) -> Self {
+        let mut levels: [(u8, Arc<str>); 8] = std::array::from_fn(|priority| {
+            let (number, text) = map_priority(Some(priority as u8));
+            (number, text.into())
+        });
+        for (priority, level) in overrides {
+            let Some(slot) = priority
+                .parse::<usize>()
+                .ok()
+                .and_then(|p| levels.get_mut(p))
+            else {
+                continue;
+            };
+            let text = level.text.as_deref().unwrap_or(severity_name(level.number));
+            *slot = (level.number, text.into());
+        }
+        let (number, text) = map_priority(None);
+        Self {
+            levels,
+            unspecified: (number, text.into()),
+        }
+    }
+
+    /// Severity number and text for `priority`; a missing or out-of-range
+    /// priority is unspecified.
+    pub fn get(&self, priority: Option<u8>) -> (u8, &str) {
+        let (number, text) = self.level(priority);
+        (*number, text)
+    }
+
+    fn level(&self, priority: Option<u8>) -> &(u8, Arc<str>) {
+        priority
+            .and_then(|p| self.levels.get(p as usize))
+            .unwrap_or(&self.unspecified)
+    }
+}
+
+impl Default for SeverityMap {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
+    use crate::test_util::serve;
 
     #[test]
     fn test_priority_mapping() {
@@ -398,6 +1488,64 @@ mod tests {
         assert_eq!(map_priority(None), (0, "UNSPECIFIED"));
     }
 
+    #[test]
+    fn test_severity_map_overrides() {
+        let overrides = BTreeMap::from([
+            (
+                "5".to_string(),
+                SeverityLevel {
+                    number: 13,
+                    text: None,
+                },
+            ),
+            (
+                "7".to_string(),
+                SeverityLevel {
+                    number: 1,
+                    text: Some("VERBOSE".to_string()),
+                },
+            ),
+        ]);
+        let map = SeverityMap::new(&overrides);
+        assert_eq!(map.get(Some(5)), (13, "WARN"));
+        assert_eq!(map.get(Some(7)), (1, "VERBOSE"));
+        // Unlisted priorities keep the built-in mapping
+        assert_eq!(map.get(Some(3)), (17, "ERROR"));
+        assert_eq!(map.get(Some(8)), (0, "UNSPECIFIED"));
+        assert_eq!(map.get(None), (0, "UNSPECIFIED"));
+        for priority in 0..=7 {
+            assert_eq!(
+                SeverityMap::default().get(Some(priority)),
+                map_priority(Some(priority))
+            );
+        }
+
+        let entry = JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            priority: Some(5),
+            ..Default::default()
+        };
+        let source = SourcePayloadOptions {
+            severity_map: map,
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "test-host",
+            &[entry.clone(), entry],
+            &HashMap::new(),
+            &source,
+            &PayloadOptions::default(),
+        );
+        let records = &payload.resource_logs[0].scope_logs[0].log_records;
+        assert_eq!(records[0].severity_number, Some(13));
+        assert_eq!(records[0].severity_text.as_deref(), Some("WARN"));
+        // Records share the map's text instead of each holding a copy
+        assert!(Arc::ptr_eq(
+            records[0].severity_text.as_ref().unwrap(),
+            records[1].severity_text.as_ref().unwrap()
+        ));
+    }
+
     #[test]
     fn test_build_payload() {
         let entries = vec![JournalEntry {
@@ -406,6 +1554,7 @@ mod tests {
             monotonic_timestamp: None,
             boot_id: Some("boot123".to_string()),
             message: "Test message".to_string(),
+            message_bytes: None,
             priority: Some(6),
             systemd_unit: Some("test.service".to_string()),
             syslog_identifier: None,
@@ -417,10 +1566,17 @@ mod tests {
             machine_id: None,
             hostname: None,
             extra_fields: HashMap::new(),
+            raw: None,
         }];
 
         let labels = HashMap::from([("env".to_string(), "test".to_string())]);
-        let payload = build_otlp_payload("test-host", &entries, &labels);
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &labels,
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        );
 
         assert_eq!(payload.resource_logs.len(), 1);
         let resource = &payload.resource_logs[0];
@@ -428,9 +1584,9 @@ mod tests {
         assert_eq!(resource.scope_logs[0].log_records.len(), 1);
 
         let record = &resource.scope_logs[0].log_records[0];
-        assert_eq!(record.body.string_value, "Test message");
-        assert_eq!(record.severity_number, 9);
-        assert_eq!(record.severity_text, "INFO");
+        assert_eq!(record.body.string_value.as_deref(), Some("Test message"));
+        assert_eq!(record.severity_number, Some(9));
+        assert_eq!(record.severity_text.as_deref(), Some("INFO"));
 
         let unit_attr = record
             .attributes
@@ -448,5 +1604,1086 @@ mod tests {
             .find(|kv| kv.key == "journald.priority.number")
             .expect("journald.priority.number attribute missing");
         assert_eq!(priority_attr.value.int_value, Some("6".to_string()));
+
+        let pid_attr = record
+            .attributes
+            .iter()
+            .find(|kv| kv.key == "process.pid")
+            .expect("process.pid attribute missing");
+        assert_eq!(pid_attr.value, AttributeValue::int(1234));
+    }
+
+    #[test]
+    fn test_os_attributes_and_label_override() {
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            message: "Test message".to_string(),
+            ..Default::default()
+        }];
+        let options = PayloadOptions {
+            os_description: Some("Fedora Linux 40".to_string()),
+            os_version: Some("40".to_string()),
+            instance_id: Some("fwd-01-4242".to_string()),
+            ..Default::default()
+        };
+        let labels = HashMap::from([("os.version".to_string(), "41".to_string())]);
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &labels,
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+
+        let attrs = &payload.resource_logs[0].resource.attributes;
+        let values = |key: &str| -> Vec<Option<String>> {
+            attrs
+                .iter()
+                .filter(|kv| kv.key == key)
+                .map(|kv| kv.value.string_value.clone())
+                .collect()
+        };
+        assert_eq!(values("os.type"), vec![Some("linux".to_string())]);
+        assert_eq!(
+            values("os.description"),
+            vec![Some("Fedora Linux 40".to_string())]
+        );
+        assert_eq!(values("os.version"), vec![Some("41".to_string())]);
+        assert_eq!(
+            values("forwarder.instance.id"),
+            vec![Some("fwd-01-4242".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_resource_fields_move_off_records() {
+        let entry = |boot: &str| JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            realtime_timestamp: 1703456789000000,
+            boot_id: Some(boot.to_string()),
+            message: "Test message".to_string(),
+            systemd_unit: Some("test.service".to_string()),
+            machine_id: Some("m1".to_string()),
+            ..Default::default()
+        };
+        let entries = vec![entry("boot-a"), entry("boot-a"), entry("boot-b")];
+        let options = PayloadOptions {
+            resource_fields: vec!["_BOOT_ID".to_string(), "_MACHINE_ID".to_string()],
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+
+        // One resource per distinct boot ID within the same service
+        assert_eq!(payload.resource_logs.len(), 2);
+        for resource in &payload.resource_logs {
+            let keys: Vec<_> = resource
+                .resource
+                .attributes
+                .iter()
+                .map(|kv| kv.key.as_str())
+                .collect();
+            assert!(keys.contains(&"systemd.boot_id"));
+            assert!(keys.contains(&"host.id"));
+
+            for record in &resource.scope_logs[0].log_records {
+                assert!(
+                    record
+                        .attributes
+                        .iter()
+                        .all(|kv| kv.key != "systemd.boot_id" && kv.key != "host.id")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_group_by_custom_field() {
+        let entry = |container: Option<&str>| JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            message: "Test message".to_string(),
+            systemd_unit: Some("containerd.service".to_string()),
+            extra_fields: container
+                .map(|c| HashMap::from([("CONTAINER_NAME".to_string(), c.to_string())]))
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        let entries = vec![entry(Some("api")), entry(Some("web")), entry(None)];
+        let options = PayloadOptions {
+            group_by: "CONTAINER_NAME".to_string(),
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+
+        let mut services: Vec<_> = payload
+            .resource_logs
+            .iter()
+            .map(|r| {
+                r.resource
+                    .attributes
+                    .iter()
+                    .find(|kv| kv.key == "service.name")
+                    .and_then(|kv| kv.value.as_str())
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        services.sort();
+        assert_eq!(services, ["api", "unknown", "web"]);
+    }
+
+    #[test]
+    fn test_service_name_fallback() {
+        let entry = |unit: Option<&str>, ident: Option<&str>, comm: Option<&str>| JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            message: "Test message".to_string(),
+            systemd_unit: unit.map(str::to_string),
+            syslog_identifier: ident.map(str::to_string),
+            comm: comm.map(str::to_string),
+            ..Default::default()
+        };
+        let entries = vec![
+            entry(Some("app.service"), Some("app"), Some("app")),
+            entry(None, Some("kernel"), None),
+            entry(None, None, Some("cron")),
+            entry(None, None, None),
+        ];
+        let services = |options: &PayloadOptions| {
+            let payload = build_otlp_payload(
+                "test-host",
+                &entries,
+                &HashMap::new(),
+                &SourcePayloadOptions::default(),
+                options,
+            );
+            let mut services: Vec<String> = payload
+                .resource_logs
+                .iter()
+                .filter_map(|r| {
+                    r.resource
+                        .attributes
+                        .iter()
+                        .find(|kv| kv.key == "service.name")
+                        .and_then(|kv| kv.value.as_str())
+                        .map(str::to_string)
+                })
+                .collect();
+            services.sort();
+            services
+        };
+
+        assert_eq!(
+            services(&PayloadOptions::default()),
+            ["app.service", "cron", "kernel", "unknown"]
+        );
+        let no_fallback = PayloadOptions {
+            service_name_fallback: Vec::new(),
+            ..Default::default()
+        };
+        assert_eq!(services(&no_fallback), ["app.service", "unknown"]);
+    }
+
+    #[test]
+    fn test_raw_json_body() {
+        let raw = r#"{"__CURSOR":"s=abc;i=1","MESSAGE":"Hi","CUSTOM":"x"}"#;
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            message: "Hi".to_string(),
+            raw: Some(raw.to_string()),
+            ..Default::default()
+        }];
+        let options = PayloadOptions {
+            body_format: BodyFormat::RawJson,
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+        let record = &payload.resource_logs[0].scope_logs[0].log_records[0];
+        assert_eq!(record.body.string_value.as_deref(), Some(raw));
+
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        );
+        let record = &payload.resource_logs[0].scope_logs[0].log_records[0];
+        assert_eq!(record.body.string_value.as_deref(), Some("Hi"));
+    }
+
+    #[test]
+    fn test_binary_message_body() {
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            message: "Hi\u{fffd}".to_string(),
+            message_bytes: Some(vec![b'H', b'i', 0xff]),
+            ..Default::default()
+        }];
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        );
+        let record = &payload.resource_logs[0].scope_logs[0].log_records[0];
+        assert_eq!(record.body.string_value.as_deref(), Some("Hi\u{fffd}"));
+        assert!(record.body.bytes_value.is_none());
+
+        let options = PayloadOptions {
+            binary_message: BinaryMessage::Bytes,
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+        let record = &payload.resource_logs[0].scope_logs[0].log_records[0];
+        assert!(record.body.string_value.is_none());
+        assert_eq!(
+            serde_json::to_value(&record.body).unwrap()["bytesValue"],
+            "SGn/"
+        );
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64::encode(b""), "");
+        assert_eq!(base64::encode(b"f"), "Zg==");
+        assert_eq!(base64::encode(b"fo"), "Zm8=");
+        assert_eq!(base64::encode(b"foo"), "Zm9v");
+        assert_eq!(base64::encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_source_timestamp() {
+        let entry = |source_ts: Option<&str>| JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            realtime_timestamp: 1_703_456_790_000_000,
+            extra_fields: source_ts
+                .map(|ts| HashMap::from([("_SOURCE_REALTIME_TIMESTAMP".to_string(), ts.into())]))
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        let options = PayloadOptions {
+            timestamp_source: TimestampSource::Source,
+            ..Default::default()
+        };
+
+        let record = build_log_record(
+            &entry(Some("1703456789000000")),
+            &SeverityMap::default(),
+            &options,
+        );
+        assert_eq!(record.time_unix_nano, "1703456789000000000");
+        let journald = record
+            .attributes
+            .iter()
+            .find(|kv| kv.key == "journald.realtime_timestamp")
+            .expect("journald time kept as attribute");
+        assert_eq!(
+            journald.value.int_value,
+            Some("1703456790000000".to_string())
+        );
+
+        // Falls back to the journald time when the source time is missing
+        let record = build_log_record(&entry(None), &SeverityMap::default(), &options);
+        assert_eq!(record.time_unix_nano, "1703456790000000000");
+
+        // The default keeps the journald receive time
+        let record = build_log_record(
+            &entry(Some("1703456789000000")),
+            &SeverityMap::default(),
+            &PayloadOptions::default(),
+        );
+        assert_eq!(record.time_unix_nano, "1703456790000000000");
+    }
+
+    #[test]
+    fn test_attribute_type_inference() {
+        let rules = vec![
+            ("_RETRY_COUNT".to_string(), AttributeType::String),
+            ("_ENABLED".to_string(), AttributeType::Bool),
+            ("_COUNT".to_string(), AttributeType::Int),
+            ("_RATIO".to_string(), AttributeType::Double),
+        ];
+        let options = PayloadOptions {
+            attribute_types: rules,
+            ..Default::default()
+        };
+        let value = |field: &str, v: &str| {
+            serde_json::to_value(typed_field_value(field, v, &options)).unwrap()
+        };
+
+        assert_eq!(
+            value("REQ_COUNT", "42"),
+            serde_json::json!({"intValue": "42"})
+        );
+        assert_eq!(
+            value("TLS_ENABLED", "true"),
+            serde_json::json!({"boolValue": true})
+        );
+        assert_eq!(
+            value("HIT_RATIO", "0.5"),
+            serde_json::json!({"doubleValue": 0.5})
+        );
+        // Longest suffix wins, and misparses or unknown suffixes stay strings
+        assert_eq!(
+            value("HTTP_RETRY_COUNT", "3"),
+            serde_json::json!({"stringValue": "3"})
+        );
+        assert_eq!(
+            value("REQ_COUNT", "many"),
+            serde_json::json!({"stringValue": "many"})
+        );
+        assert_eq!(value("OTHER", "1"), serde_json::json!({"stringValue": "1"}));
+    }
+
+    #[test]
+    fn test_known_int_fields() {
+        let entry = JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            pid: Some("1234".to_string()),
+            uid: Some("1000".to_string()),
+            gid: Some("100".to_string()),
+            extra_fields: HashMap::from([
+                ("ERRNO".to_string(), "2".to_string()),
+                ("SYSLOG_PID".to_string(), "n/a".to_string()),
+                ("APP_VERSION".to_string(), "1.2.3".to_string()),
+                ("REQUEST_ID".to_string(), "42".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let attr = |record: &LogRecord, key: &str| {
+            record
+                .attributes
+                .iter()
+                .find(|kv| kv.key == key)
+                .map(|kv| kv.value.clone())
+                .unwrap_or_else(|| panic!("{key} attribute missing"))
+        };
+
+        let record = build_log_record(&entry, &SeverityMap::default(), &PayloadOptions::default());
+        assert_eq!(attr(&record, "process.pid"), AttributeValue::int(1234));
+        assert_eq!(attr(&record, "process.owner"), AttributeValue::int(1000));
+        assert_eq!(attr(&record, "process.group.id"), AttributeValue::int(100));
+        assert_eq!(attr(&record, "errno"), AttributeValue::int(2));
+        // Listed fields that don't parse, and unlisted ones, stay strings
+        assert_eq!(attr(&record, "syslog.pid"), AttributeValue::string("n/a"));
+        assert_eq!(
+            attr(&record, "app.version"),
+            AttributeValue::string("1.2.3")
+        );
+        assert_eq!(attr(&record, "request.id"), AttributeValue::string("42"));
+
+        // infer_int_fields extends that to any plain integer
+        let mut entry = entry;
+        entry
+            .extra_fields
+            .insert("ORDER_ID".to_string(), "007".to_string());
+        let options = PayloadOptions {
+            infer_int_fields: true,
+            ..Default::default()
+        };
+        let record = build_log_record(&entry, &SeverityMap::default(), &options);
+        assert_eq!(attr(&record, "request.id"), AttributeValue::int(42));
+        assert_eq!(attr(&record, "order.id"), AttributeValue::string("007"));
+        assert_eq!(
+            attr(&record, "app.version"),
+            AttributeValue::string("1.2.3")
+        );
+
+        // A matching attribute_types rule takes precedence
+        let options = PayloadOptions {
+            attribute_types: vec![("_PID".to_string(), AttributeType::String)],
+            ..Default::default()
+        };
+        let record = build_log_record(&entry, &SeverityMap::default(), &options);
+        assert_eq!(attr(&record, "process.pid"), AttributeValue::string("1234"));
+
+        // Promoted to the resource, the field keeps its type
+        let options = PayloadOptions {
+            resource_fields: vec!["_PID".to_string()],
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "host",
+            &[entry],
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+        let pid = payload.resource_logs[0]
+            .resource
+            .attributes
+            .iter()
+            .find(|kv| kv.key == "process.pid")
+            .expect("process.pid resource attribute missing");
+        assert_eq!(pid.value, AttributeValue::int(1234));
+    }
+
+    #[test]
+    fn test_enricher_rewrites_attributes() {
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            hostname: Some("web-01".to_string()),
+            ..Default::default()
+        }];
+        let options = PayloadOptions {
+            enricher: Some(Enricher::new(|entry, attributes| {
+                attributes.retain(|kv| kv.key != "systemd.cursor");
+                let dc = match entry.hostname.as_deref() {
+                    Some(h) if h.starts_with("web-") => "dc1",
+                    _ => "unknown",
+                };
+                attributes.push(KeyValue {
+                    key: "datacenter".to_string(),
+                    value: AttributeValue::string(dc),
+                });
+            })),
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+        let attributes = &payload.resource_logs[0].scope_logs[0].log_records[0].attributes;
+        assert!(!attributes.iter().any(|kv| kv.key == "systemd.cursor"));
+        let dc = attributes.iter().find(|kv| kv.key == "datacenter").unwrap();
+        assert_eq!(dc.value.as_str(), Some("dc1"));
+    }
+
+    #[test]
+    fn test_resource_attributes_with_label_override() {
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            ..Default::default()
+        }];
+        let options = PayloadOptions {
+            resource_attributes: HashMap::from([
+                ("cloud.region".to_string(), "eu-west-1".to_string()),
+                ("k8s.cluster.name".to_string(), "prod".to_string()),
+                ("os.type".to_string(), "freebsd".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let labels = HashMap::from([("cloud.region".to_string(), "us-east-1".to_string())]);
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &labels,
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+
+        let attrs = &payload.resource_logs[0].resource.attributes;
+        let values = |key: &str| -> Vec<Option<&str>> {
+            attrs
+                .iter()
+                .filter(|kv| kv.key == key)
+                .map(|kv| kv.value.as_str())
+                .collect()
+        };
+        assert_eq!(values("cloud.region"), vec![Some("us-east-1")]);
+        assert_eq!(values("k8s.cluster.name"), vec![Some("prod")]);
+        assert_eq!(values("os.type"), vec![Some("freebsd")]);
+    }
+
+    #[test]
+    fn test_code_attributes() {
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            extra_fields: HashMap::from([
+                ("CODE_FILE".to_string(), "src/main.rs".to_string()),
+                ("CODE_LINE".to_string(), "42".to_string()),
+                ("CODE_FUNC".to_string(), "main".to_string()),
+            ]),
+            ..Default::default()
+        }];
+        let options = PayloadOptions {
+            code_attributes: true,
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+        let record = &payload.resource_logs[0].scope_logs[0].log_records[0];
+        let value = |key: &str| {
+            record
+                .attributes
+                .iter()
+                .find(|kv| kv.key == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(
+            value("code.filepath"),
+            Some(AttributeValue::string("src/main.rs"))
+        );
+        assert_eq!(value("code.lineno"), Some(AttributeValue::int(42)));
+        assert_eq!(value("code.function"), Some(AttributeValue::string("main")));
+        assert!(value("code.line").is_none());
+
+        // Disabled by default: generic lowercase-dot keys, with CODE_LINE
+        // still an int as a known numeric field
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        );
+        let record = &payload.resource_logs[0].scope_logs[0].log_records[0];
+        let line = record.attributes.iter().find(|kv| kv.key == "code.line");
+        assert_eq!(line.unwrap().value, AttributeValue::int(42));
+    }
+
+    #[test]
+    fn test_mark_after_cursor_reset() {
+        let entries: Vec<JournalEntry> = ["a.service", "b.service"]
+            .iter()
+            .map(|unit| JournalEntry {
+                cursor: "s=abc;i=1".to_string(),
+                systemd_unit: Some(unit.to_string()),
+                ..Default::default()
+            })
+            .collect();
+        let mut payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        );
+        mark_after_cursor_reset(&mut payload);
+
+        let records: Vec<&LogRecord> = payload
+            .resource_logs
+            .iter()
+            .flat_map(|r| &r.scope_logs)
+            .flat_map(|s| &s.log_records)
+            .collect();
+        assert_eq!(records.len(), 2);
+        for record in records {
+            let flag = record
+                .attributes
+                .iter()
+                .find(|kv| kv.key == "ojgf.after_cursor_reset");
+            assert_eq!(flag.unwrap().value, AttributeValue::bool(true));
+        }
+    }
+
+    #[test]
+    fn test_severity_fields_toggle() {
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            priority: Some(3),
+            ..Default::default()
+        }];
+        let record_json = |options: &PayloadOptions| {
+            let payload = build_otlp_payload(
+                "test-host",
+                &entries,
+                &HashMap::new(),
+                &SourcePayloadOptions::default(),
+                options,
+            );
+            serde_json::to_value(&payload.resource_logs[0].scope_logs[0].log_records[0]).unwrap()
+        };
+
+        let both = record_json(&PayloadOptions::default());
+        assert_eq!(both["severityNumber"], 17);
+        assert_eq!(both["severityText"], "ERROR");
+
+        let number_only = record_json(&PayloadOptions {
+            severity_text: false,
+            ..Default::default()
+        });
+        assert_eq!(number_only["severityNumber"], 17);
+        assert!(number_only.get("severityText").is_none());
+
+        let text_only = record_json(&PayloadOptions {
+            severity_number: false,
+            ..Default::default()
+        });
+        assert!(text_only.get("severityNumber").is_none());
+        assert_eq!(text_only["severityText"], "ERROR");
+    }
+
+    #[test]
+    fn test_max_extra_fields_keeps_first_by_name() {
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            extra_fields: HashMap::from([
+                ("ZETA".to_string(), "z".to_string()),
+                ("ALPHA".to_string(), "a".to_string()),
+                ("BETA".to_string(), "b".to_string()),
+            ]),
+            ..Default::default()
+        }];
+        let options = PayloadOptions {
+            max_extra_fields: Some(2),
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+        let record = &payload.resource_logs[0].scope_logs[0].log_records[0];
+        let has = |key: &str| record.attributes.iter().any(|kv| kv.key == key);
+        assert!(has("alpha") && has("beta") && !has("zeta"));
+        assert_eq!(record.dropped_attributes_count, 1);
+
+        let json = serde_json::to_value(record).unwrap();
+        assert_eq!(json["droppedAttributesCount"], 1);
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        );
+        let record = &payload.resource_logs[0].scope_logs[0].log_records[0];
+        assert!(
+            serde_json::to_value(record)
+                .unwrap()
+                .get("droppedAttributesCount")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_max_records_per_scope_splits_groups() {
+        let entries: Vec<JournalEntry> = (0..5)
+            .map(|i| JournalEntry {
+                cursor: format!("s=abc;i={}", i),
+                systemd_unit: Some("app.service".to_string()),
+                ..Default::default()
+            })
+            .collect();
+        let options = PayloadOptions {
+            max_records_per_scope: Some(2),
+            ..Default::default()
+        };
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+
+        let sizes: Vec<usize> = payload
+            .resource_logs
+            .iter()
+            .map(|r| r.scope_logs[0].log_records.len())
+            .collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+        assert!(
+            payload
+                .resource_logs
+                .iter()
+                .all(|r| r.resource.attributes.len()
+                    == payload.resource_logs[0].resource.attributes.len())
+        );
+    }
+
+    #[test]
+    fn test_scope_name_override() {
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            ..Default::default()
+        }];
+        let options = PayloadOptions::default();
+
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &options,
+        );
+        let scope = &payload.resource_logs[0].scope_logs[0].scope;
+        assert_eq!(scope.name, "otel-journal-gatewayd-forwarder");
+
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions {
+                scope_name: Some("edge-pipeline".to_string()),
+                ..Default::default()
+            },
+            &options,
+        );
+        let scope = &payload.resource_logs[0].scope_logs[0].scope;
+        assert_eq!(scope.name, "edge-pipeline");
+        assert_eq!(scope.version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn test_logs_url() {
+        assert_eq!(logs_url("http://h:4318", None), "http://h:4318/v1/logs");
+        assert_eq!(logs_url("http://h:4318/", None), "http://h:4318/v1/logs");
+        // Already-suffixed endpoints are not doubled
+        assert_eq!(logs_url("http://h/v1/logs", None), "http://h/v1/logs");
+        assert_eq!(logs_url("http://h/v1/logs/", None), "http://h/v1/logs");
+        assert_eq!(
+            logs_url("http://h/otlp", Some("/api/logs")),
+            "http://h/otlp/api/logs"
+        );
+        assert_eq!(logs_url("http://h/ingest", Some("")), "http://h/ingest");
+    }
+
+    #[test]
+    fn test_debug_dump_writes_and_rotates() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("dump.jsonl");
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            message: "Dumped".to_string(),
+            ..Default::default()
+        }];
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        );
+
+        let dump = DebugDump::open(&path, 1).unwrap();
+        dump.write("test-host", &payload).unwrap();
+        let line = std::fs::read_to_string(&path).unwrap();
+        let value: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(value["source"], "test-host");
+        assert_eq!(value["record"]["body"]["stringValue"], "Dumped");
+
+        // Exceeding the bound moves the current file aside
+        dump.write("test-host", &payload).unwrap();
+        assert!(dir.path().join("dump.jsonl.1").exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_request_id_header() {
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (url, requests) = serve(vec![ok.to_string(); 2]);
+
+        let client = OtlpClient::new(
+            &url,
+            None,
+            None,
+            &HashMap::new(),
+            OtlpTimeouts::default(),
+            PayloadOptions::default(),
+        )
+        .unwrap()
+        .with_request_id_header("X-Request-ID");
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            message: "Hi".to_string(),
+            ..Default::default()
+        }];
+
+        for _ in 0..2 {
+            client
+                .send(
+                    "h",
+                    "h",
+                    Batch::new(&entries),
+                    &HashMap::new(),
+                    &SourcePayloadOptions::default(),
+                )
+                .unwrap();
+        }
+        let requests = requests.lock();
+        let first = requests[0].header("x-request-id").unwrap();
+        let second = requests[1].header("x-request-id").unwrap();
+        assert_eq!(first.len(), 36);
+        assert_eq!(&first[14..15], "4");
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_loki_payload_format() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        ]);
+
+        let client = OtlpClient::new(
+            &url,
+            None,
+            None,
+            &HashMap::new(),
+            OtlpTimeouts::default(),
+            PayloadOptions {
+                format: PayloadFormat::Loki,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            realtime_timestamp: 1_700_000_000_000_000,
+            message: "Hi".to_string(),
+            priority: Some(6),
+            systemd_unit: Some("sshd.service".to_string()),
+            ..Default::default()
+        }];
+        client
+            .send(
+                "h",
+                "host-01",
+                Batch::new(&entries),
+                &HashMap::new(),
+                &SourcePayloadOptions::default(),
+            )
+            .unwrap();
+
+        let request = &requests.lock()[0];
+        assert!(request.head.starts_with("POST /loki/api/v1/push "));
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"streams": [{
+                "stream": {"host": "host-01", "priority": "6", "unit": "sshd.service"},
+                "values": [["1700000000000000000", "Hi"]],
+            }]})
+        );
+    }
+
+    #[test]
+    fn test_cursor_range() {
+        let entries: Vec<JournalEntry> = (1..=3)
+            .map(|i| JournalEntry {
+                cursor: format!("s=abc;i={}", i),
+                message: "Hi".to_string(),
+                ..Default::default()
+            })
+            .collect();
+
+        let mut payload = build_otlp_payload(
+            "h",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        );
+        mark_cursor_range(&mut payload, &entries);
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(
+            json["resourceLogs"][0]["scopeLogs"][0]["scope"]["attributes"],
+            serde_json::json!([
+                {"key": "ojgf.cursor.first", "value": {"stringValue": "s=abc;i=1"}},
+                {"key": "ojgf.cursor.last", "value": {"stringValue": "s=abc;i=3"}},
+            ])
+        );
+
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        ]);
+        let client = OtlpClient::new(
+            &url,
+            None,
+            None,
+            &HashMap::new(),
+            OtlpTimeouts::default(),
+            PayloadOptions::default(),
+        )
+        .unwrap()
+        .with_cursor_range(CursorRange::Headers);
+        client
+            .send(
+                "h",
+                "h",
+                Batch::new(&entries),
+                &HashMap::new(),
+                &SourcePayloadOptions::default(),
+            )
+            .unwrap();
+        let request = &requests.lock()[0];
+        assert_eq!(request.header("x-ojgf-first-cursor"), Some("s=abc;i=1"));
+        assert_eq!(request.header("x-ojgf-last-cursor"), Some("s=abc;i=3"));
+        assert!(!String::from_utf8_lossy(&request.body).contains("ojgf.cursor.first"));
+    }
+
+    #[test]
+    fn test_error_is_transient() {
+        let server = |status| OtlpError::ServerError {
+            status,
+            body: String::new(),
+        };
+        assert!(server(StatusCode::SERVICE_UNAVAILABLE).is_transient());
+        assert!(server(StatusCode::INTERNAL_SERVER_ERROR).is_transient());
+        assert!(!server(StatusCode::BAD_REQUEST).is_transient());
+        assert!(!server(StatusCode::TOO_MANY_REQUESTS).is_transient());
+        assert!(!OtlpError::Config("bad".to_string()).is_transient());
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = humantime::parse_rfc3339("1994-11-06T08:49:00Z").unwrap();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+            Some(Duration::from_secs(37))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:48:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(
+            parse_retry_after("Sun, 06 Foo 1994 08:49:37 GMT", now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_throttled_with_retry_after() {
+        let (url, _) = serve(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 7\r\nContent-Length: 0\r\n\r\n"
+                .to_string(),
+        ]);
+
+        let client = OtlpClient::new(
+            &url,
+            None,
+            None,
+            &HashMap::new(),
+            OtlpTimeouts::default(),
+            PayloadOptions::default(),
+        )
+        .unwrap();
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            message: "hello".to_string(),
+            ..Default::default()
+        }];
+        let err = client
+            .send(
+                "host",
+                "host",
+                Batch::new(&entries),
+                &HashMap::new(),
+                &SourcePayloadOptions::default(),
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            OtlpError::Throttled { retry_after } if retry_after == Duration::from_secs(7)
+        ));
+        assert!(!err.is_transient());
+    }
+
+    #[test]
+    fn test_gzip_round_trip() {
+        use std::io::Read;
+        let json = serde_json::to_string(&build_otlp_payload(
+            "test-host",
+            &[JournalEntry {
+                cursor: "s=abc;i=1".to_string(),
+                message: "hello ".repeat(100),
+                ..Default::default()
+            }],
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        ))
+        .unwrap();
+
+        let compressed = gzip(&json).unwrap();
+        assert!(compressed.len() < json.len());
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, json);
+    }
+
+    #[test]
+    fn test_gzip_falls_back_after_415() {
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 415 Unsupported Media Type\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+            ok.to_string(),
+            ok.to_string(),
+        ]);
+        let gzipped =
+            |index: usize| requests.lock()[index].header("content-encoding") == Some("gzip");
+
+        let client = OtlpClient::new(
+            &url,
+            None,
+            None,
+            &HashMap::new(),
+            OtlpTimeouts::default(),
+            PayloadOptions::default(),
+        )
+        .unwrap()
+        .with_compression(OtlpCompression::Gzip);
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            message: "Compressed".to_string(),
+            ..Default::default()
+        }];
+
+        client
+            .send(
+                "test-host",
+                "test-host",
+                Batch::new(&entries),
+                &HashMap::new(),
+                &SourcePayloadOptions::default(),
+            )
+            .unwrap();
+        assert!(gzipped(0));
+        assert!(!gzipped(1));
+
+        // Compression stays off for the rest of the session
+        client
+            .send(
+                "test-host",
+                "test-host",
+                Batch::new(&entries),
+                &HashMap::new(),
+                &SourcePayloadOptions::default(),
+            )
+            .unwrap();
+        assert!(!gzipped(2));
     }
 }
@@ -0,0 +1,594 @@
+//! OTLP/gRPC transport for the same batches.
+//!
+//! With `otlp_protocol = "grpc"` the client calls
+//! `opentelemetry.proto.collector.logs.v1.LogsService/Export` on
+//! `otlp_endpoint` with a protobuf body instead of POSTing JSON. Collectors
+//! stay blocking; a one-worker tokio runtime drives the tonic channel.
+
+use super::{
+    AnyValue, AttributeValue, ExportLogsServiceRequest, KeyValue, OtlpError, OtlpTimeouts,
+};
+use crate::config::TlsConfig;
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::time::Duration;
+use tonic::codec::{CompressionEncoding, ProstCodec};
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue, MetadataMap};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+use tonic::{Code, Status};
+use tracing::warn;
+
+/// gRPC method for OTLP log exports.
+const EXPORT_PATH: &str = "/opentelemetry.proto.collector.logs.v1.LogsService/Export";
+
+/// Blocking OTLP/gRPC log exporter.
+pub(super) struct GrpcExporter {
+    runtime: tokio::runtime::Runtime,
+    channel: Channel,
+    /// `otlp_headers`, sent as metadata on every call.
+    metadata: MetadataMap,
+}
+
+impl GrpcExporter {
+    /// Prepare a lazily connected channel to `endpoint`; TLS is used for
+    /// `https://` endpoints.
+    pub(super) fn new(
+        endpoint: &str,
+        tls: Option<&TlsConfig>,
+        headers: &HashMap<String, String>,
+        timeouts: OtlpTimeouts,
+    ) -> Result<Self, OtlpError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("otlp-grpc")
+            .enable_all()
+            .build()
+            .map_err(|e| OtlpError::Config(format!("failed to start gRPC runtime: {}", e)))?;
+
+        let mut builder = Endpoint::from_shared(endpoint.trim_end_matches('/').to_string())
+            .map_err(|e| OtlpError::Config(format!("invalid gRPC endpoint '{}': {}", endpoint, e)))?
+            .connect_timeout(timeouts.connect)
+            .timeout(timeouts.request);
+        if endpoint.starts_with("https://") {
+            builder = builder
+                .tls_config(client_tls(tls)?)
+                .map_err(|e| OtlpError::Config(format!("gRPC TLS: {}", e)))?;
+        }
+        // The channel's background tasks are spawned on the runtime
+        let channel = {
+            let _guard = runtime.enter();
+            builder.connect_lazy()
+        };
+
+        let mut metadata = MetadataMap::new();
+        for (name, value) in headers {
+            insert_metadata(&mut metadata, name, value)?;
+        }
+
+        Ok(Self {
+            runtime,
+            channel,
+            metadata,
+        })
+    }
+
+    /// Export the encoded `payload` with per-call `headers` as metadata, gzip-encoding it
+    /// when `compress` is set and giving up after `timeout` if given.
+    pub(super) fn export(
+        &self,
+        payload: proto::ExportLogsServiceRequest,
+        headers: &[(&str, &str)],
+        compress: bool,
+        timeout: Option<Duration>,
+    ) -> Result<(), OtlpError> {
+        let mut request = tonic::Request::new(payload);
+        *request.metadata_mut() = self.metadata.clone();
+        for (name, value) in headers {
+            insert_metadata(request.metadata_mut(), name, value)?;
+        }
+
+        let mut client = tonic::client::Grpc::new(self.channel.clone());
+        if compress {
+            client = client.send_compressed(CompressionEncoding::Gzip);
+        }
+        let call = async move {
+            client
+                .ready()
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+            client
+                .unary::<_, proto::ExportLogsServiceResponse, _>(
+                    request,
+                    http::uri::PathAndQuery::from_static(EXPORT_PATH),
+                    ProstCodec::default(),
+                )
+                .await
+        };
+        let response = self
+            .runtime
+            .block_on(async {
+                match timeout {
+                    Some(timeout) => match tokio::time::timeout(timeout, call).await {
+                        Ok(result) => result,
+                        Err(_) => Err(Status::deadline_exceeded("export timed out")),
+                    },
+                    None => call.await,
+                }
+            })
+            .map_err(|status| {
+                warn!(
+                    code = ?status.code(),
+                    message = status.message(),
+                    "OTLP gRPC endpoint rejected request"
+                );
+                status_error(&status)
+            })?;
+
+        if let Some(partial) = response.into_inner().partial_success
+            && partial.rejected_log_records > 0
+        {
+            warn!(
+                rejected = partial.rejected_log_records,
+                message = %partial.error_message,
+                "OTLP gRPC endpoint rejected some log records"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Roots, CA and client identity for an `https://` endpoint.
+fn client_tls(tls: Option<&TlsConfig>) -> Result<ClientTlsConfig, OtlpError> {
+    let read = |path: &std::path::Path| {
+        std::fs::read(path).map_err(|e| OtlpError::Config(format!("{}: {}", path.display(), e)))
+    };
+    let mut config = ClientTlsConfig::new().with_webpki_roots();
+    if let Some(t) = tls {
+        if let Some(ca) = &t.ca_cert {
+            config = config.ca_certificate(Certificate::from_pem(read(ca)?));
+        }
+        if let (Some(cert), Some(key)) = (&t.client_cert, &t.client_key) {
+            config = config.identity(Identity::from_pem(read(cert)?, read(key)?));
+        }
+    }
+    Ok(config)
+}
+
+fn insert_metadata(metadata: &mut MetadataMap, name: &str, value: &str) -> Result<(), OtlpError> {
+    let key = AsciiMetadataKey::from_bytes(name.to_ascii_lowercase().as_bytes())
+        .map_err(|e| OtlpError::Config(format!("invalid gRPC metadata key '{}': {}", name, e)))?;
+    let value = AsciiMetadataValue::try_from(value).map_err(|e| {
+        OtlpError::Config(format!("invalid gRPC metadata value for '{}': {}", name, e))
+    })?;
+    metadata.insert(key, value);
+    Ok(())
+}
+
+/// Report a failed call as the HTTP status gateways use for its gRPC code, so
+/// retries treat both transports alike. The codes the OTLP spec calls
+/// retryable map to 503/504; `ResourceExhausted` is retryable only with a
+/// `google.rpc.RetryInfo` delay, which (on any retryable code) becomes
+/// [`OtlpError::Throttled`].
+fn status_error(status: &Status) -> OtlpError {
+    let http_status = match status.code() {
+        Code::Cancelled
+        | Code::Aborted
+        | Code::OutOfRange
+        | Code::DataLoss
+        | Code::Unavailable
+        | Code::DeadlineExceeded
+        | Code::ResourceExhausted
+            if let Some(retry_after) = retry_delay(status) =>
+        {
+            return OtlpError::Throttled { retry_after };
+        }
+        Code::Cancelled | Code::Aborted | Code::OutOfRange | Code::DataLoss | Code::Unavailable => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::InvalidArgument | Code::FailedPrecondition => StatusCode::BAD_REQUEST,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists => StatusCode::CONFLICT,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Ok | Code::Unknown | Code::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    OtlpError::ServerError {
+        status: http_status,
+        body: format!("{:?}: {}", status.code(), status.message()),
+    }
+}
+
+/// The `google.rpc.RetryInfo` delay carried in the status details, if any.
+fn retry_delay(status: &Status) -> Option<Duration> {
+    use prost::Message;
+    let details = proto::RpcStatus::decode(status.details()).ok()?;
+    let info = details
+        .details
+        .iter()
+        .find(|any| any.type_url.ends_with("/google.rpc.RetryInfo"))?;
+    let delay = proto::RetryInfo::decode(info.value.as_slice())
+        .ok()?
+        .retry_delay?;
+    Some(Duration::new(
+        delay.seconds.max(0) as u64,
+        delay.nanos.clamp(0, 999_999_999) as u32,
+    ))
+}
+
+// ============================================================================
+// Protobuf Messages
+// ============================================================================
+
+/// The subset of `opentelemetry/proto` the forwarder sends, with the upstream
+/// field numbers.
+pub(super) mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ExportLogsServiceRequest {
+        #[prost(message, repeated, tag = "1")]
+        pub resource_logs: Vec<ResourceLogs>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ExportLogsServiceResponse {
+        #[prost(message, optional, tag = "1")]
+        pub partial_success: Option<ExportLogsPartialSuccess>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ExportLogsPartialSuccess {
+        #[prost(int64, tag = "1")]
+        pub rejected_log_records: i64,
+        #[prost(string, tag = "2")]
+        pub error_message: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ResourceLogs {
+        #[prost(message, optional, tag = "1")]
+        pub resource: Option<Resource>,
+        #[prost(message, repeated, tag = "2")]
+        pub scope_logs: Vec<ScopeLogs>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Resource {
+        #[prost(message, repeated, tag = "1")]
+        pub attributes: Vec<KeyValue>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ScopeLogs {
+        #[prost(message, optional, tag = "1")]
+        pub scope: Option<InstrumentationScope>,
+        #[prost(message, repeated, tag = "2")]
+        pub log_records: Vec<LogRecord>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InstrumentationScope {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub version: String,
+        #[prost(message, repeated, tag = "3")]
+        pub attributes: Vec<KeyValue>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LogRecord {
+        #[prost(fixed64, tag = "1")]
+        pub time_unix_nano: u64,
+        #[prost(int32, tag = "2")]
+        pub severity_number: i32,
+        #[prost(string, tag = "3")]
+        pub severity_text: String,
+        #[prost(message, optional, tag = "5")]
+        pub body: Option<AnyValue>,
+        #[prost(message, repeated, tag = "6")]
+        pub attributes: Vec<KeyValue>,
+        #[prost(uint32, tag = "7")]
+        pub dropped_attributes_count: u32,
+        #[prost(fixed64, tag = "11")]
+        pub observed_time_unix_nano: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct KeyValue {
+        #[prost(string, tag = "1")]
+        pub key: String,
+        #[prost(message, optional, tag = "2")]
+        pub value: Option<AnyValue>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AnyValue {
+        #[prost(oneof = "Value", tags = "1, 2, 3, 4, 7")]
+        pub value: Option<Value>,
+    }
+
+    /// `google.rpc.Status`, as sent in the `grpc-status-details-bin` trailer.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RpcStatus {
+        #[prost(int32, tag = "1")]
+        pub code: i32,
+        #[prost(string, tag = "2")]
+        pub message: String,
+        #[prost(message, repeated, tag = "3")]
+        pub details: Vec<Any>,
+    }
+
+    /// `google.protobuf.Any`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Any {
+        #[prost(string, tag = "1")]
+        pub type_url: String,
+        #[prost(bytes = "vec", tag = "2")]
+        pub value: Vec<u8>,
+    }
+
+    /// `google.rpc.RetryInfo`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RetryInfo {
+        #[prost(message, optional, tag = "1")]
+        pub retry_delay: Option<ProtoDuration>,
+    }
+
+    /// `google.protobuf.Duration`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ProtoDuration {
+        #[prost(int64, tag = "1")]
+        pub seconds: i64,
+        #[prost(int32, tag = "2")]
+        pub nanos: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Value {
+        #[prost(string, tag = "1")]
+        String(String),
+        #[prost(bool, tag = "2")]
+        Bool(bool),
+        #[prost(int64, tag = "3")]
+        Int(i64),
+        #[prost(double, tag = "4")]
+        Double(f64),
+        #[prost(bytes = "vec", tag = "7")]
+        Bytes(Vec<u8>),
+    }
+}
+
+impl From<&ExportLogsServiceRequest> for proto::ExportLogsServiceRequest {
+    fn from(request: &ExportLogsServiceRequest) -> Self {
+        Self {
+            resource_logs: request
+                .resource_logs
+                .iter()
+                .map(|resource_logs| proto::ResourceLogs {
+                    resource: Some(proto::Resource {
+                        attributes: key_values(&resource_logs.resource.attributes),
+                    }),
+                    scope_logs: resource_logs
+                        .scope_logs
+                        .iter()
+                        .map(|scope_logs| proto::ScopeLogs {
+                            scope: Some(proto::InstrumentationScope {
+                                name: scope_logs.scope.name.clone(),
+                                version: scope_logs.scope.version.clone(),
+                                attributes: key_values(&scope_logs.scope.attributes),
+                            }),
+                            log_records: scope_logs
+                                .log_records
+                                .iter()
+                                .map(|record| proto::LogRecord {
+                                    // OTLP/JSON carries the fixed64 timestamps as strings
+                                    time_unix_nano: record.time_unix_nano.parse().unwrap_or(0),
+                                    observed_time_unix_nano: record
+                                        .observed_time_unix_nano
+                                        .parse()
+                                        .unwrap_or(0),
+                                    severity_number: record.severity_number.map_or(0, i32::from),
+                                    severity_text: record
+                                        .severity_text
+                                        .as_deref()
+                                        .unwrap_or_default()
+                                        .to_string(),
+                                    body: Some(any_value(&record.body)),
+                                    attributes: key_values(&record.attributes),
+                                    dropped_attributes_count: record.dropped_attributes_count,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn key_values(attributes: &[KeyValue]) -> Vec<proto::KeyValue> {
+    attributes
+        .iter()
+        .map(|kv| proto::KeyValue {
+            key: kv.key.clone(),
+            value: Some(attribute_value(&kv.value)),
+        })
+        .collect()
+}
+
+fn attribute_value(value: &AttributeValue) -> proto::AnyValue {
+    let value = if let Some(s) = &value.string_value {
+        proto::Value::String(s.clone())
+    } else if let Some(i) = value.int_value.as_deref().and_then(|i| i.parse().ok()) {
+        proto::Value::Int(i)
+    } else if let Some(b) = value.bool_value {
+        proto::Value::Bool(b)
+    } else if let Some(d) = value.double_value {
+        proto::Value::Double(d)
+    } else {
+        return proto::AnyValue { value: None };
+    };
+    proto::AnyValue { value: Some(value) }
+}
+
+fn any_value(value: &AnyValue) -> proto::AnyValue {
+    let value = match (&value.string_value, &value.bytes_value) {
+        (Some(s), _) => Some(proto::Value::String(s.clone())),
+        (None, Some(bytes)) => Some(proto::Value::Bytes(bytes.clone())),
+        (None, None) => None,
+    };
+    proto::AnyValue { value }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::JournalEntry;
+    use crate::otlp::{PayloadOptions, SourcePayloadOptions, build_otlp_payload};
+    use prost::Message;
+
+    #[test]
+    fn test_proto_conversion() {
+        let entries = vec![JournalEntry {
+            cursor: "s=abc;i=1".to_string(),
+            realtime_timestamp: 1_700_000_000_000_000,
+            message: "Hi".to_string(),
+            priority: Some(3),
+            systemd_unit: Some("nginx.service".to_string()),
+            pid: Some("42".to_string()),
+            ..Default::default()
+        }];
+        let payload = build_otlp_payload(
+            "test-host",
+            &entries,
+            &HashMap::new(),
+            &SourcePayloadOptions::default(),
+            &PayloadOptions::default(),
+        );
+
+        let encoded = proto::ExportLogsServiceRequest::from(&payload).encode_to_vec();
+        let decoded = proto::ExportLogsServiceRequest::decode(encoded.as_slice()).unwrap();
+
+        let resource_logs = &decoded.resource_logs[0];
+        let resource = resource_logs.resource.as_ref().unwrap();
+        assert!(resource.attributes.iter().any(|kv| kv.key == "host.name"
+            && kv.value.as_ref().unwrap().value
+                == Some(proto::Value::String("test-host".to_string()))));
+
+        let scope_logs = &resource_logs.scope_logs[0];
+        assert_eq!(
+            scope_logs.scope.as_ref().unwrap().name,
+            crate::otlp::DEFAULT_SCOPE_NAME
+        );
+        let record = &scope_logs.log_records[0];
+        assert_eq!(record.time_unix_nano, 1_700_000_000_000_000_000);
+        assert_eq!(record.severity_number, 17);
+        assert_eq!(record.severity_text, "ERROR");
+        assert_eq!(
+            record.body.as_ref().unwrap().value,
+            Some(proto::Value::String("Hi".to_string()))
+        );
+        let pid = record
+            .attributes
+            .iter()
+            .find(|kv| kv.key == "process.pid")
+            .unwrap();
+        assert_eq!(
+            pid.value.as_ref().unwrap().value,
+            Some(proto::Value::Int(42))
+        );
+        assert_eq!(
+            attribute_value(&AttributeValue::int(-7)).value,
+            Some(proto::Value::Int(-7))
+        );
+    }
+
+    #[test]
+    fn test_status_error() {
+        let cases = [
+            (Code::Unavailable, StatusCode::SERVICE_UNAVAILABLE),
+            (Code::Cancelled, StatusCode::SERVICE_UNAVAILABLE),
+            (Code::Aborted, StatusCode::SERVICE_UNAVAILABLE),
+            (Code::OutOfRange, StatusCode::SERVICE_UNAVAILABLE),
+            (Code::DataLoss, StatusCode::SERVICE_UNAVAILABLE),
+            (Code::DeadlineExceeded, StatusCode::GATEWAY_TIMEOUT),
+            (Code::ResourceExhausted, StatusCode::TOO_MANY_REQUESTS),
+            (Code::InvalidArgument, StatusCode::BAD_REQUEST),
+            (Code::Unauthenticated, StatusCode::UNAUTHORIZED),
+            (Code::AlreadyExists, StatusCode::CONFLICT),
+        ];
+        for (code, expected) in cases {
+            match status_error(&Status::new(code, "nope")) {
+                err @ OtlpError::ServerError { status, .. } if status == expected => {
+                    let retryable = !matches!(
+                        code,
+                        Code::ResourceExhausted
+                            | Code::InvalidArgument
+                            | Code::Unauthenticated
+                            | Code::AlreadyExists
+                    );
+                    assert_eq!(err.is_transient(), retryable, "{:?}", code);
+                    assert!(err.to_string().ends_with(": nope"));
+                }
+                other => panic!("unexpected error for {:?}: {}", code, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_status_error_retry_info() {
+        use prost::Message;
+        let retry_info = proto::RetryInfo {
+            retry_delay: Some(proto::ProtoDuration {
+                seconds: 3,
+                nanos: 500_000_000,
+            }),
+        };
+        let details = proto::RpcStatus {
+            code: Code::ResourceExhausted as i32,
+            message: "slow down".to_string(),
+            details: vec![proto::Any {
+                type_url: "type.googleapis.com/google.rpc.RetryInfo".to_string(),
+                value: retry_info.encode_to_vec(),
+            }],
+        }
+        .encode_to_vec();
+        for code in [Code::ResourceExhausted, Code::Unavailable] {
+            let status = Status::with_details(code, "slow down", details.clone().into());
+            assert!(matches!(
+                status_error(&status),
+                OtlpError::Throttled { retry_after } if retry_after == Duration::from_millis(3500)
+            ));
+        }
+        // Not a retryable code: the hint is ignored
+        let status = Status::with_details(Code::InvalidArgument, "bad", details.into());
+        assert!(matches!(
+            status_error(&status),
+            OtlpError::ServerError { status, .. } if status == StatusCode::BAD_REQUEST
+        ));
+    }
+
+    #[test]
+    fn test_export_unreachable_endpoint() {
+        let exporter = GrpcExporter::new(
+            "http://127.0.0.1:1",
+            None,
+            &HashMap::from([("Authorization".to_string(), "Bearer x".to_string())]),
+            OtlpTimeouts::default(),
+        )
+        .unwrap();
+        let payload = proto::ExportLogsServiceRequest {
+            resource_logs: Vec::new(),
+        };
+        let err = exporter.export(payload, &[], false, None).unwrap_err();
+        assert!(matches!(
+            err,
+            OtlpError::ServerError {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+//! Source URL policy: reject sources that resolve to disallowed networks.
+//!
+//! Meant for deployments that build configs from tenant-supplied URLs, where
+//! a source aimed at loopback, a metadata service or an internal range must
+//! never be polled. The check runs during config validation and again when a
+//! collector builds its HTTP client, which then connects only to the vetted
+//! addresses and does not follow redirects.
+
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::str::FromStr;
+
+/// Networks denied when `[source_policy]` is present without a `deny` list:
+/// unspecified, loopback, link-local (incl. cloud metadata), RFC 1918,
+/// shared address space and IPv6 unique-local.
+const DEFAULT_DENY: &[&str] = &[
+    "0.0.0.0/8",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "100.64.0.0/10",
+    "::/128",
+    "::1/128",
+    "fe80::/10",
+    "fc00::/7",
+];
+
+/// An IPv4 or IPv6 network in CIDR notation. A bare address is a /32 or /128.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Whether `ip` falls inside this network. IPv4-mapped IPv6 addresses
+    /// match IPv4 networks.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| format!("invalid CIDR '{}': bad address", s))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| format!("invalid CIDR '{}': prefix must be 0-{}", s, max))?,
+            None => max,
+        };
+        Ok(Cidr { addr, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// `[source_policy]`: which networks source URLs may point at. An address
+/// is rejected if it matches `deny` and no `allow` entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SourcePolicy {
+    /// Denied networks; defaults to loopback, link-local and private ranges.
+    #[serde(default = "default_deny")]
+    pub deny: Vec<Cidr>,
+    /// Exceptions to `deny`.
+    #[serde(default)]
+    pub allow: Vec<Cidr>,
+}
+
+impl Default for SourcePolicy {
+    fn default() -> Self {
+        SourcePolicy {
+            deny: default_deny(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+fn default_deny() -> Vec<Cidr> {
+    DEFAULT_DENY
+        .iter()
+        .map(|c| c.parse().expect("valid built-in CIDR"))
+        .collect()
+}
+
+impl SourcePolicy {
+    /// Whether the policy permits connecting to `ip`.
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        !self.deny.iter().any(|c| c.contains(ip)) || self.allow.iter().any(|c| c.contains(ip))
+    }
+
+    /// Resolve the host of `url` and check every address it maps to. Fails
+    /// closed: a host that does not resolve is rejected.
+    pub fn check_url(&self, url: &str) -> Result<(), String> {
+        self.vetted_addrs(url).map(|_| ())
+    }
+
+    /// Like [`check_url`](Self::check_url), but returns the host and the
+    /// addresses it resolved to so connections can be pinned to them.
+    pub fn vetted_addrs(&self, url: &str) -> Result<(String, Vec<SocketAddr>), String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("invalid URL: {}", e))?;
+        let port = parsed.port_or_known_default().unwrap_or(80);
+        let host = parsed.host_str().ok_or("URL has no host")?;
+        // IPv6 literals keep their brackets in host_str
+        let addrs: Vec<SocketAddr> = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+            Ok(ip) => vec![(ip, port).into()],
+            Err(_) => (host, port)
+                .to_socket_addrs()
+                .map_err(|e| format!("cannot resolve '{}': {}", host, e))?
+                .collect(),
+        };
+        if addrs.is_empty() {
+            return Err(format!("'{}' resolved to no addresses", host));
+        }
+        self.check_addrs(&addrs)?;
+        Ok((host.to_string(), addrs))
+    }
+
+    /// Reject the first address the policy does not permit.
+    pub fn check_addrs(&self, addrs: &[SocketAddr]) -> Result<(), String> {
+        match addrs.iter().find(|a| !self.permits(a.ip())) {
+            Some(a) => Err(format!(
+                "resolves to {}, which is denied by source_policy",
+                a.ip()
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cidr_parse_and_contains() {
+        let net: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert!(net.contains("10.1.2.3".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+        assert!(net.contains("::ffff:10.0.0.1".parse().unwrap()));
+
+        let host: Cidr = "169.254.169.254".parse().unwrap();
+        assert_eq!(host.to_string(), "169.254.169.254/32");
+        assert!(host.contains("169.254.169.254".parse().unwrap()));
+        assert!(!host.contains("169.254.169.253".parse().unwrap()));
+
+        let all: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains("203.0.113.9".parse().unwrap()));
+        assert!(!all.contains("::1".parse().unwrap()));
+
+        let ula: Cidr = "fc00::/7".parse().unwrap();
+        assert!(ula.contains("fd12::1".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("not-an-ip/8".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = SourcePolicy::default();
+        assert!(policy.check_url("http://169.254.169.254/latest").is_err());
+        assert!(policy.check_url("http://127.0.0.1:19531").is_err());
+        assert!(policy.check_url("http://[::1]:19531").is_err());
+        assert!(policy.check_url("http://192.168.1.10:19531").is_err());
+        assert!(policy.check_url("http://203.0.113.10:19531").is_ok());
+        assert!(policy.check_url("http://localhost:19531").is_err());
+    }
+
+    #[test]
+    fn test_allow_overrides_deny() {
+        let policy = SourcePolicy {
+            allow: vec![
+                "127.0.0.1".parse().unwrap(),
+                "10.20.0.0/16".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+        assert!(policy.check_url("http://127.0.0.1:19531").is_ok());
+        assert!(policy.check_url("http://10.20.4.4:19531").is_ok());
+        assert!(policy.check_url("http://10.21.4.4:19531").is_err());
+    }
+}
@@ -0,0 +1,540 @@
+//! Collector threads for the configured sources.
+//!
+//! The supervisor propagates shutdown to every collector and waits up to
+//! `shutdown_grace` for them, optionally talks to systemd (watchdog pings
+//! while sources keep ticking, `STOPPING=1`), and applies a reloaded
+//! configuration:
+//! new sources get a collector, removed ones are stopped, changed ones are
+//! restarted once their old collector has flushed, and label or
+//! `poll_interval` changes are handed to running collectors in place.
+
+use crate::audit::FilterAudit;
+use crate::collector::{self, Collector, LiveUpdate, LiveUpdates, UnackedBudget};
+use crate::config::{Config, Source};
+use crate::cursor::{self, SqliteStore};
+use crate::journal::RequestSigner;
+use crate::metrics::MetricsState;
+use crate::otlp::OtlpClient;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Shared pieces every collector is built from, kept so sources added on
+/// reload are set up like the ones started with the process.
+pub(crate) struct CollectorParts {
+    pub otlp: Arc<OtlpClient>,
+    pub metrics: Option<Arc<MetricsState>>,
+    pub request_signer: Option<RequestSigner>,
+    pub filter_audit: Option<Arc<FilterAudit>>,
+    pub unacked_budget: Option<Arc<UnackedBudget>>,
+    /// Cursor directory locked at startup; a reload cannot move it.
+    pub cursor_dir: PathBuf,
+    pub cursor_store: Option<Arc<SqliteStore>>,
+}
+
+impl CollectorParts {
+    /// Build the collector for `source` with its cursor.
+    pub fn build(
+        &self,
+        source: Source,
+        config: &Config,
+    ) -> Result<Collector, Box<dyn std::error::Error>> {
+        let cursor = match &self.cursor_store {
+            Some(store) => cursor::CursorManager::sqlite(store.clone(), &source.name),
+            None => cursor::CursorManager::new(&self.cursor_dir, &source.name)?,
+        };
+        let mut collector = Collector::new(
+            source,
+            config,
+            self.otlp.clone(),
+            cursor,
+            self.metrics.clone(),
+        )?;
+        if let Some(signer) = &self.request_signer {
+            collector = collector.with_request_signer(signer.clone());
+        }
+        if let Some(audit) = &self.filter_audit {
+            collector = collector.with_filter_audit(audit.clone());
+        }
+        if let Some(budget) = &self.unacked_budget {
+            collector = collector.with_unacked_budget(budget.clone());
+        }
+        Ok(collector)
+    }
+}
+
+/// Where a reload reads the configuration from, and the counter that asks
+/// for one (bumped on SIGHUP).
+pub(crate) struct ReloadSource {
+    pub path: PathBuf,
+    pub requests: Arc<AtomicU64>,
+}
+
+/// systemd notifications sent on the embedder's behalf.
+pub(crate) struct Systemd {
+    /// Watchdog timeout from `WATCHDOG_USEC`, if the watchdog is enabled.
+    pub watchdog: Option<Duration>,
+}
+
+/// A running collector thread.
+struct SourceThread {
+    name: String,
+    poll_interval: Duration,
+    /// Unix ms of the collector's last completed cycle.
+    tick: Arc<AtomicU64>,
+    /// Stops this collector alone; raised for every one on shutdown.
+    stop: Arc<AtomicBool>,
+    live_updates: LiveUpdates,
+    handle: thread::JoinHandle<()>,
+}
+
+pub(crate) struct Supervisor {
+    /// Configuration the running collectors were built from.
+    config: Config,
+    parts: CollectorParts,
+    shutdown: Arc<AtomicBool>,
+    polled_ok: Arc<AtomicBool>,
+    status_requests: Arc<AtomicU64>,
+    once: bool,
+    running: Vec<SourceThread>,
+    /// Removed sources' collectors that have not finished yet.
+    retiring: Vec<SourceThread>,
+}
+
+impl Supervisor {
+    pub fn new(
+        config: Config,
+        parts: CollectorParts,
+        shutdown: Arc<AtomicBool>,
+        polled_ok: Arc<AtomicBool>,
+        status_requests: Arc<AtomicU64>,
+        once: bool,
+    ) -> Self {
+        Self {
+            config,
+            parts,
+            shutdown,
+            polled_ok,
+            status_requests,
+            once,
+            running: Vec::new(),
+            retiring: Vec::new(),
+        }
+    }
+
+    /// Run `collector` on its own thread, after `after` (the source's
+    /// previous collector) has finished, so two never share a cursor.
+    pub fn spawn(&mut self, collector: Collector, after: Option<thread::JoinHandle<()>>) {
+        let name = collector.source_name().to_string();
+        let poll_interval = self.config.poll_interval;
+        let stop = Arc::new(AtomicBool::new(false));
+        let tick = Arc::new(AtomicU64::new(crate::current_unix_ms()));
+        let live_updates = collector.live_updates();
+
+        let (thread_stop, thread_tick) = (stop.clone(), tick.clone());
+        let polled_ok = self.polled_ok.clone();
+        let status_requests = self.status_requests.clone();
+        let once = self.once;
+        let handle = thread::spawn(move || {
+            if let Some(previous) = after {
+                let _ = previous.join();
+            }
+            collector::run_loop(
+                collector,
+                poll_interval,
+                thread_stop,
+                once,
+                thread_tick,
+                status_requests,
+                polled_ok,
+            );
+        });
+
+        self.running.push(SourceThread {
+            name,
+            poll_interval,
+            tick,
+            stop,
+            live_updates,
+            handle,
+        });
+    }
+
+    /// Wait for the collectors to finish, stopping them on shutdown and
+    /// reloading when asked. With `systemd`, the watchdog is pinged every
+    /// half its timeout and `STOPPING=1` is sent when shutdown begins.
+    ///
+    /// Returns the sources whose collectors had not stopped `shutdown_grace`
+    /// after shutdown; their threads are left running.
+    pub fn run(mut self, reload: Option<ReloadSource>, systemd: Option<Systemd>) -> Vec<String> {
+        let watchdog = systemd.as_ref().and_then(|s| s.watchdog);
+        // Zero stands for the configuration the supervisor was built with,
+        // so a request made while starting up still triggers a reload
+        let mut reloads_seen = 0;
+        let mut next_ping = Instant::now();
+
+        while !self.shutdown.load(Ordering::Relaxed) {
+            if let Some(reload) = &reload {
+                let requested = reload.requests.load(Ordering::Relaxed);
+                if reloads_seen != requested {
+                    reloads_seen = requested;
+                    self.reload(&reload.path);
+                }
+            }
+            if let Some(timeout) = watchdog
+                && Instant::now() >= next_ping
+            {
+                self.ping_watchdog();
+                next_ping = Instant::now() + timeout / 2;
+            }
+
+            // Collectors stop on their own with --once
+            reap(&mut self.running);
+            reap(&mut self.retiring);
+            if self.running.is_empty() && self.retiring.is_empty() {
+                return Vec::new();
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        // Tell systemd the stop is underway so it doesn't expect watchdog pings
+        #[cfg(unix)]
+        if systemd.is_some()
+            && let Err(e) = sd_notify::notify(false, &[sd_notify::NotifyState::Stopping])
+        {
+            warn!(error = %e, "Failed to send systemd stopping notification");
+        }
+
+        let mut sources: Vec<SourceThread> =
+            self.running.into_iter().chain(self.retiring).collect();
+        for source in &sources {
+            source.stop.store(true, Ordering::Relaxed);
+        }
+        let deadline = Instant::now() + self.config.shutdown_grace;
+        loop {
+            reap(&mut sources);
+            if sources.is_empty() || Instant::now() >= deadline {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        sources.into_iter().map(|source| source.name).collect()
+    }
+
+    /// Ping the systemd watchdog if every running source has ticked within
+    /// its own freshness window.
+    fn ping_watchdog(&self) {
+        let now = crate::current_unix_ms();
+        let all_fresh = self.running.iter().all(|source| {
+            let window = source
+                .poll_interval
+                .saturating_mul(5)
+                .max(Duration::from_secs(60));
+            now.saturating_sub(source.tick.load(Ordering::Relaxed)) <= window.as_millis() as u64
+        });
+        if !all_fresh {
+            warn!("Skipping systemd watchdog ping: one or more sources appear stale");
+            return;
+        }
+        #[cfg(unix)]
+        if let Err(e) = sd_notify::notify(false, &[sd_notify::NotifyState::Watchdog]) {
+            warn!(error = %e, "Failed to send systemd watchdog notification");
+        }
+    }
+
+    /// Re-read the configuration at `path` and apply its source changes.
+    /// An invalid file leaves everything running as it was.
+    fn reload(&mut self, path: &Path) {
+        info!(path = %path.display(), "Reloading configuration");
+        let new = match Config::load(&path.to_path_buf()).and_then(|c| c.validate().map(|_| c)) {
+            Ok(new) => new,
+            Err(e) => {
+                error!(error = %e, "Configuration reload failed; keeping the running configuration");
+                return;
+            }
+        };
+        for warning in new.warnings() {
+            warn!("Configuration warning: {}", warning);
+        }
+
+        let plan = self.config.reload_plan(&new);
+        for setting in &plan.restart_required {
+            warn!(setting = %setting, "Changed setting takes effect after a restart");
+        }
+
+        // Only source changes are applied; other settings keep running values
+        let mut applied = self.config.clone();
+        applied.sources = new.sources;
+        applied.poll_interval = new.poll_interval;
+        let source = |name: &str| applied.sources.iter().find(|s| s.name == name).cloned();
+
+        for name in &plan.removed {
+            if let Some(index) = self.running.iter().position(|s| &s.name == name) {
+                info!(source = %name, "Source removed, stopping its collector");
+                let removed = self.running.remove(index);
+                removed.stop.store(true, Ordering::Relaxed);
+                self.retiring.push(removed);
+            }
+        }
+
+        let mut started = Vec::new();
+        // Sources whose new definition has no collector yet
+        let mut unbuilt = Vec::new();
+        for name in &plan.restarted {
+            let Some(index) = self.running.iter().position(|s| &s.name == name) else {
+                continue;
+            };
+            match self
+                .parts
+                .build(source(name).expect("planned source"), &applied)
+            {
+                Ok(collector) => {
+                    info!(source = %name, "Source changed, restarting its collector");
+                    let previous = self.running.remove(index);
+                    previous.stop.store(true, Ordering::Relaxed);
+                    started.push((collector, Some(previous.handle)));
+                }
+                Err(e) => {
+                    error!(source = %name, error = %e, "Failed to rebuild collector; keeping the running one");
+                    unbuilt.push(name);
+                }
+            }
+        }
+        for name in &plan.added {
+            match self
+                .parts
+                .build(source(name).expect("planned source"), &applied)
+            {
+                Ok(collector) => {
+                    info!(source = %name, "Source added, starting its collector");
+                    // A collector removed by an earlier reload may still be
+                    // flushing with the same cursor
+                    let previous = self
+                        .retiring
+                        .iter()
+                        .position(|s| &s.name == name)
+                        .map(|index| self.retiring.remove(index).handle);
+                    started.push((collector, previous));
+                }
+                Err(e) => {
+                    error!(source = %name, error = %e, "Failed to start collector");
+                    unbuilt.push(name);
+                }
+            }
+        }
+
+        for running in &mut self.running {
+            if !plan.poll_interval_changed && !plan.relabeled.contains(&running.name) {
+                continue;
+            }
+            let Some(source) = source(&running.name) else {
+                continue;
+            };
+            running.poll_interval = applied.poll_interval;
+            *running.live_updates.lock() = Some(LiveUpdate {
+                poll_interval: applied.poll_interval,
+                labels: source.labels,
+            });
+        }
+
+        // Keep what is actually running, so the next reload retries them
+        for name in unbuilt {
+            let old = self.config.sources.iter().find(|s| &s.name == name);
+            let index = applied.sources.iter().position(|s| &s.name == name);
+            match (index, old) {
+                (Some(index), Some(old)) => applied.sources[index] = old.clone(),
+                (Some(index), None) => {
+                    applied.sources.remove(index);
+                }
+                (None, _) => {}
+            }
+        }
+
+        self.config = applied;
+        for (collector, previous) in started {
+            self.spawn(collector, previous);
+        }
+        if let Some(metrics) = &self.parts.metrics {
+            metrics.set_config_hash(self.config.hash());
+        }
+        info!(
+            added = plan.added.len(),
+            removed = plan.removed.len(),
+            restarted = plan.restarted.len(),
+            relabeled = plan.relabeled.len(),
+            poll_interval_changed = plan.poll_interval_changed,
+            config_hash = %self.config.hash(),
+            "Configuration reloaded"
+        );
+    }
+}
+
+/// Join and drop the finished collectors in `sources`.
+fn reap(sources: &mut Vec<SourceThread>) {
+    let mut index = 0;
+    while index < sources.len() {
+        if sources[index].handle.is_finished() {
+            join(sources.remove(index));
+        } else {
+            index += 1;
+        }
+    }
+}
+
+fn join(source: SourceThread) {
+    if let Err(e) = source.handle.join() {
+        warn!(source = %source.name, panic = ?e, "Collector thread panicked");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(path: &Path, cursor_dir: &Path, sources: &[(&str, u16)]) {
+        let mut content = format!(
+            "otlp_endpoint = \"http://127.0.0.1:1\"\ncursor_dir = \"{}\"\n",
+            cursor_dir.display()
+        );
+        for (name, port) in sources {
+            content.push_str(&format!(
+                "\n[[sources]]\nname = \"{}\"\nurl = \"http://127.0.0.1:{}\"\n",
+                name, port
+            ));
+        }
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn names(threads: &[SourceThread]) -> Vec<&str> {
+        let mut names: Vec<&str> = threads.iter().map(|s| s.name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_reload_starts_stops_and_restarts_collectors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        write_config(&path, dir.path(), &[("a", 1), ("b", 1)]);
+        let config = Config::load(&path).unwrap();
+        let parts = CollectorParts {
+            otlp: Arc::new(crate::build_otlp_client(&config, None).unwrap()),
+            metrics: None,
+            request_signer: None,
+            filter_audit: None,
+            unacked_budget: None,
+            cursor_dir: dir.path().to_path_buf(),
+            cursor_store: None,
+        };
+        let collectors: Vec<Collector> = config
+            .sources
+            .iter()
+            .map(|s| parts.build(s.clone(), &config).unwrap())
+            .collect();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let mut supervisor = Supervisor::new(
+            config,
+            parts,
+            shutdown.clone(),
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicU64::new(0)),
+            false,
+        );
+        for collector in collectors {
+            supervisor.spawn(collector, None);
+        }
+
+        // b removed, c added, a's URL changed
+        write_config(&path, dir.path(), &[("a", 2), ("c", 1)]);
+        supervisor.reload(&path);
+        assert_eq!(names(&supervisor.running), ["a", "c"]);
+        assert_eq!(names(&supervisor.retiring), ["b"]);
+        assert!(supervisor.retiring[0].stop.load(Ordering::Relaxed));
+        assert_eq!(supervisor.config.sources[0].url, "http://127.0.0.1:2");
+
+        // b comes back before its old collector is reaped: the new one
+        // waits for it instead of leaving it in `retiring`
+        write_config(&path, dir.path(), &[("a", 2), ("b", 1), ("c", 1)]);
+        supervisor.reload(&path);
+        assert_eq!(names(&supervisor.running), ["a", "b", "c"]);
+        assert!(supervisor.retiring.is_empty());
+
+        // An invalid file keeps everything running
+        std::fs::write(&path, "not toml").unwrap();
+        supervisor.reload(&path);
+        assert_eq!(names(&supervisor.running), ["a", "b", "c"]);
+
+        shutdown.store(true, Ordering::Relaxed);
+        assert!(supervisor.run(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_reload_retries_sources_that_failed_to_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        write_config(&path, dir.path(), &[("a", 1)]);
+        let config = Config::load(&path).unwrap();
+        let parts = CollectorParts {
+            otlp: Arc::new(crate::build_otlp_client(&config, None).unwrap()),
+            metrics: None,
+            request_signer: None,
+            filter_audit: None,
+            unacked_budget: None,
+            cursor_dir: dir.path().to_path_buf(),
+            cursor_store: None,
+        };
+        let collector = parts.build(config.sources[0].clone(), &config).unwrap();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let mut supervisor = Supervisor::new(
+            config,
+            parts,
+            shutdown.clone(),
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicU64::new(0)),
+            false,
+        );
+        supervisor.spawn(collector, None);
+
+        // a changed and b added, both with a token file that is missing, so
+        // neither collector can be built
+        let token = dir.path().join("token");
+        write_config(&path, dir.path(), &[("a", 1), ("b", 1)]);
+        let content = std::fs::read_to_string(&path).unwrap().replace(
+            "url = \"http://127.0.0.1:1\"\n",
+            &format!(
+                "url = \"http://127.0.0.1:1\"\nbearer_token_file = \"{}\"\n",
+                token.display()
+            ),
+        );
+        std::fs::write(&path, &content).unwrap();
+        supervisor.reload(&path);
+        assert_eq!(names(&supervisor.running), ["a"]);
+        let sources: Vec<&str> = supervisor
+            .config
+            .sources
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(sources, ["a"]);
+        assert!(supervisor.config.sources[0].bearer_token_file.is_none());
+
+        // The same file applies once the collectors can be built
+        std::fs::write(&token, "secret").unwrap();
+        supervisor.reload(&path);
+        assert_eq!(names(&supervisor.running), ["a", "b"]);
+        assert_eq!(supervisor.config.sources.len(), 2);
+        assert!(
+            supervisor
+                .config
+                .sources
+                .iter()
+                .all(|s| s.bearer_token_file.is_some())
+        );
+
+        shutdown.store(true, Ordering::Relaxed);
+        assert!(supervisor.run(None, None).is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+//! Mock HTTP server shared by the unit tests.
+
+use parking_lot::Mutex;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::Arc;
+
+/// A request received by [`serve`].
+#[derive(Debug, Clone)]
+pub(crate) struct Request {
+    /// Request line and headers, as sent.
+    pub head: String,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Value of header `name`, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+}
+
+/// Serve canned HTTP responses, one per connection, and return the base URL
+/// and the requests received so far.
+///
+/// Each request is read in full (headers, then `Content-Length` bytes of
+/// body) and recorded before its response is written.
+pub(crate) fn serve(responses: Vec<String>) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let served = Arc::new(Mutex::new(Vec::new()));
+    let requests = served.clone();
+    std::thread::spawn(move || {
+        for response in responses {
+            let Ok((stream, _)) = listener.accept() else {
+                return;
+            };
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            let mut head = String::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap_or(0);
+                }
+                head.push_str(&line);
+                line.clear();
+            }
+            let mut body = Vec::new();
+            let _ = reader.by_ref().take(length).read_to_end(&mut body);
+            requests.lock().push(Request { head, body });
+            let _ = reader.get_mut().write_all(response.as_bytes());
+        }
+    });
+    (url, served)
+}
+
+/// Serve a single canned response and return the base URL.
+pub(crate) fn serve_once(response: String) -> String {
+    serve(vec![response]).0
+}